@@ -0,0 +1,176 @@
+//! Benchmarks of `set`, `set_many`, `draw` and `query_leaves` over K and the
+//! number of leaves, to pick K for a tree.
+//!
+//! Each group reports one line per tree size with K on the x axis, so the
+//! HTML report (`target/criterion/report/index.html`) shows where the cheaper
+//! `set` of a wide tree stops paying for its more expensive `draw`. Trees go
+//! up to 10^7 leaves, which takes a while and a few GB of memory; set
+//! `SORTITION_BENCH_MAX_LEAVES` to stop earlier:
+//!
+//! ```sh
+//! SORTITION_BENCH_MAX_LEAVES=100000 cargo bench --bench sortition_sum_tree
+//! ```
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use sortition_sum_tree::SortitionSumTrees;
+
+type Trees = SortitionSumTrees<u64, u64, u64>;
+
+const KS: [usize; 6] = [2, 4, 8, 16, 32, 64];
+const KEY: u64 = 0;
+
+/// xorshift64, so that IDs and numbers do not follow the leaf order.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+fn sizes() -> Vec<u64> {
+    let max = std::env::var("SORTITION_BENCH_MAX_LEAVES")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(10_000_000);
+    (2..=7)
+        .map(|exponent| 10u64.pow(exponent))
+        .filter(|&n| n <= max)
+        .collect()
+}
+
+fn label(n: u64) -> String {
+    format!("1e{} leaves", n.ilog10())
+}
+
+/// Stakes between 1 and 1000, so that draws end up in every subtree.
+fn stake(id: u64) -> u64 {
+    id.wrapping_mul(0x9e37_79b9_7f4a_7c15) % 1_000 + 1
+}
+
+fn tree(k: usize, n: u64) -> Trees {
+    let mut trees = Trees::new();
+    trees.create_tree(KEY, k).unwrap();
+    for id in 0..n {
+        trees.set(&KEY, stake(id), id).unwrap();
+    }
+    trees
+}
+
+/// Runs `bench` on a tree of every size and K, building each tree once.
+fn each_tree(
+    c: &mut Criterion,
+    group: &str,
+    mut bench: impl FnMut(&mut Trees, u64, &mut Rng) -> u64,
+) {
+    let mut group = c.benchmark_group(group);
+    for n in sizes() {
+        if n >= 1_000_000 {
+            group.sample_size(10);
+        }
+        for k in KS {
+            let mut trees = tree(k, n);
+            let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+            group.bench_with_input(BenchmarkId::new(label(n), k), &n, |b, &n| {
+                b.iter(|| black_box(bench(&mut trees, n, &mut rng)))
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Appending `n` IDs to an empty tree, i.e. inserts without vacant leaves.
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    for n in sizes() {
+        group.throughput(Throughput::Elements(n));
+        if n >= 100_000 {
+            group.sample_size(10);
+        }
+        for k in KS {
+            group.bench_with_input(BenchmarkId::new(label(n), k), &n, |b, &n| {
+                b.iter_batched(
+                    || {
+                        let mut trees = Trees::new();
+                        trees.create_tree(KEY, k).unwrap();
+                        trees
+                    },
+                    |mut trees| {
+                        for id in 0..n {
+                            trees.set(&KEY, stake(id), id).unwrap();
+                        }
+                        trees
+                    },
+                    BatchSize::PerIteration,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Changing the stake of an existing ID.
+fn update(c: &mut Criterion) {
+    each_tree(c, "update", |trees, n, rng| {
+        let id = rng.below(n);
+        trees.set(&KEY, rng.below(1_000) + 1, id).unwrap();
+        id
+    });
+}
+
+/// Removing an ID, then inserting it again into the leaf it vacated.
+fn remove_and_reinsert(c: &mut Criterion) {
+    each_tree(c, "remove_and_reinsert", |trees, n, rng| {
+        let id = rng.below(n);
+        trees.set(&KEY, 0, id).unwrap();
+        trees.set(&KEY, stake(id), id).unwrap();
+        id
+    });
+}
+
+/// Changing the stakes of 1000 existing IDs in one `set_many`.
+fn batch_update(c: &mut Criterion) {
+    each_tree(c, "batch_update", |trees, n, rng| {
+        let entries: Vec<(u64, u64)> = (0..1_000)
+            .map(|_| (rng.below(n), rng.below(1_000) + 1))
+            .collect();
+        trees.set_many(&KEY, entries).unwrap();
+        n
+    });
+}
+
+fn draw(c: &mut Criterion) {
+    each_tree(c, "draw", |trees, _, rng| {
+        trees.draw(&KEY, rng.next()).unwrap()
+    });
+}
+
+/// A page of 100 leaves at a random cursor.
+fn query_leaves(c: &mut Criterion) {
+    each_tree(c, "query_leaves", |trees, n, rng| {
+        let page = trees
+            .query_leaves(&KEY, rng.below(n) as usize, 100)
+            .unwrap();
+        page.entries.len() as u64
+    });
+}
+
+criterion_group!(
+    benches,
+    insert,
+    update,
+    remove_and_reinsert,
+    batch_update,
+    draw,
+    query_leaves
+);
+criterion_main!(benches);
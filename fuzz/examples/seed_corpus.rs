@@ -0,0 +1,111 @@
+//! Writes the seed corpus of the fuzz targets, built from the sequences of
+//! the library tests: `cargo run --example seed_corpus` from `fuzz/`.
+
+use std::fs;
+use std::path::Path;
+
+use borsh::BorshSerialize;
+use sortition_sum_tree_fuzz::{run, Op};
+
+fn set(id: u8, value: u64) -> Op {
+    Op::Set { key: 1, id, value }
+}
+
+fn draw(number: u64) -> Op {
+    Op::Draw { key: 1, number }
+}
+
+fn four_leaves() -> Vec<Op> {
+    let mut ops = vec![Op::CreateTree { key: 1, k: 2 }];
+    ops.extend((1..=4).map(|id| set(id, 25)));
+    ops
+}
+
+fn seeds() -> Vec<(&'static str, Vec<Op>)> {
+    let build_tree = four_leaves();
+
+    let mut remove_and_add_node = four_leaves();
+    remove_and_add_node.extend([set(3, 0), Op::StakeOf { key: 1, id: 3 }, set(5, 25)]);
+
+    let mut draw_four = four_leaves();
+    draw_four.extend([draw(20), draw(40), draw(60), draw(80)]);
+
+    let errors = vec![
+        set(1, 25),
+        draw(0),
+        Op::CreateTree { key: 1, k: 1 },
+        Op::CreateTree { key: 1, k: 2 },
+        Op::CreateTree { key: 1, k: 2 },
+        draw(0),
+        set(1, 25),
+        set(1, 0),
+        draw(0),
+    ];
+
+    let mut overflow = vec![Op::CreateTree { key: 1, k: 2 }];
+    overflow.extend([set(1, u64::MAX), set(2, 1), set(1, u64::MAX - 1), set(2, 1)]);
+
+    let mut query_leaves = vec![Op::CreateTree { key: 1, k: 3 }];
+    query_leaves.extend((1..=9).map(|id| set(id, id as u64 * 10)));
+    query_leaves.extend([set(4, 0), set(7, 0)]);
+    query_leaves.extend((0..4).map(|cursor| Op::QueryLeaves {
+        key: 1,
+        cursor: cursor * 2,
+        count: 2,
+    }));
+
+    // Relocations at every level, with vacant leaves reused in between.
+    let mut relocations = Vec::new();
+    for k in 2..=5 {
+        relocations.push(Op::CreateTree { key: k - 2, k });
+        for id in 0..40 {
+            relocations.push(Op::Set {
+                key: k - 2,
+                id,
+                value: id as u64 % 7 + 1,
+            });
+            if id % 5 == 4 {
+                relocations.push(Op::Set {
+                    key: k - 2,
+                    id: id - 2,
+                    value: 0,
+                });
+            }
+        }
+        relocations.push(Op::Draw {
+            key: k - 2,
+            number: 1_000,
+        });
+    }
+
+    vec![
+        ("build_tree", build_tree),
+        ("remove_and_add_node", remove_and_add_node),
+        ("draw", draw_four),
+        ("errors", errors),
+        ("overflow", overflow),
+        ("query_leaves", query_leaves),
+        ("relocations", relocations),
+    ]
+}
+
+fn main() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus");
+    fs::create_dir_all(corpus.join("operations")).unwrap();
+    fs::create_dir_all(corpus.join("snapshot")).unwrap();
+    for (name, ops) in seeds() {
+        let data = Op::encode(&ops);
+        assert_eq!(Op::decode(&data), ops);
+        fs::write(corpus.join("operations").join(name), data).unwrap();
+        let trees = run(&ops);
+        for key in 0..4 {
+            if let Ok(tree) = trees.tree(&key) {
+                fs::write(
+                    corpus.join("snapshot").join(format!("{name}_{key}")),
+                    tree.try_to_vec().unwrap(),
+                )
+                .unwrap();
+            }
+        }
+    }
+}
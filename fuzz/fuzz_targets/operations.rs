@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sortition_sum_tree_fuzz::{run, Op};
+
+fuzz_target!(|data: &[u8]| {
+    run(&Op::decode(data));
+});
@@ -0,0 +1,26 @@
+#![no_main]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use libfuzzer_sys::fuzz_target;
+use sortition_sum_tree::{NodeStore, SortitionSumTree};
+
+// Any snapshot the loader accepts must be a tree that `set` could have built.
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut tree) = SortitionSumTree::<u8, u64>::try_from_slice(data) else {
+        return;
+    };
+    assert!(tree.validate().is_empty());
+    let bytes = tree.try_to_vec().unwrap();
+    assert_eq!(SortitionSumTree::try_from_slice(&bytes).unwrap(), tree);
+
+    let total = tree.total();
+    for number in 0..total.min(64) {
+        let id = NodeStore::draw(&tree, number).unwrap();
+        assert!(NodeStore::stake_of(&tree, &id) > 0);
+    }
+    for id in 0..8 {
+        if NodeStore::set(&mut tree, u64::from(id) + 1, id).is_ok() {
+            assert!(tree.validate().is_empty());
+        }
+    }
+});
@@ -0,0 +1,195 @@
+//! Operation sequences for the fuzz targets, decoded from raw bytes.
+//!
+//! Each operation starts with an opcode byte followed by its fixed-size
+//! arguments; a truncated last operation is dropped. The format is kept
+//! byte-oriented, rather than derived, so that `seed_corpus` can write inputs
+//! that any libFuzzer mutation stays close to.
+
+use std::collections::HashMap;
+
+use sortition_sum_tree::{SortitionError, SortitionSumTrees};
+
+/// Trees are keyed by a byte, so that a few keys cover several trees.
+pub type Trees = SortitionSumTrees<u8, u8, u64>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// K is taken modulo 10, so that invalid Ks are reachable.
+    CreateTree {
+        key: u8,
+        k: u8,
+    },
+    Set {
+        key: u8,
+        id: u8,
+        value: u64,
+    },
+    Draw {
+        key: u8,
+        number: u64,
+    },
+    StakeOf {
+        key: u8,
+        id: u8,
+    },
+    QueryLeaves {
+        key: u8,
+        cursor: u8,
+        count: u8,
+    },
+}
+
+impl Op {
+    pub fn decode(data: &[u8]) -> Vec<Op> {
+        let mut ops = Vec::new();
+        let mut bytes = data;
+        while let Some((&opcode, rest)) = bytes.split_first() {
+            let (op, size) = match opcode % 5 {
+                0 => (
+                    rest.get(..2).map(|b| Op::CreateTree {
+                        key: b[0] % 4,
+                        k: b[1] % 10,
+                    }),
+                    2,
+                ),
+                1 => (
+                    rest.get(..10).map(|b| Op::Set {
+                        key: b[0] % 4,
+                        id: b[1],
+                        value: u64::from_le_bytes(b[2..10].try_into().unwrap()),
+                    }),
+                    10,
+                ),
+                2 => (
+                    rest.get(..9).map(|b| Op::Draw {
+                        key: b[0] % 4,
+                        number: u64::from_le_bytes(b[1..9].try_into().unwrap()),
+                    }),
+                    9,
+                ),
+                3 => (
+                    rest.get(..2).map(|b| Op::StakeOf {
+                        key: b[0] % 4,
+                        id: b[1],
+                    }),
+                    2,
+                ),
+                _ => (
+                    rest.get(..3).map(|b| Op::QueryLeaves {
+                        key: b[0] % 4,
+                        cursor: b[1],
+                        count: b[2],
+                    }),
+                    3,
+                ),
+            };
+            match op {
+                Some(op) => ops.push(op),
+                None => break,
+            }
+            bytes = &rest[size..];
+        }
+        ops
+    }
+
+    pub fn encode(ops: &[Op]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for op in ops {
+            match *op {
+                Op::CreateTree { key, k } => data.extend([0, key, k]),
+                Op::Set { key, id, value } => {
+                    data.extend([1, key, id]);
+                    data.extend(value.to_le_bytes());
+                }
+                Op::Draw { key, number } => {
+                    data.extend([2, key]);
+                    data.extend(number.to_le_bytes());
+                }
+                Op::StakeOf { key, id } => data.extend([3, key, id]),
+                Op::QueryLeaves { key, cursor, count } => data.extend([4, key, cursor, count]),
+            }
+        }
+        data
+    }
+}
+
+/// Applies the operations to `trees`, checking after every step that the
+/// trees agree with a map of the stakes and that `validate` finds nothing.
+pub fn run(ops: &[Op]) -> Trees {
+    let mut trees = Trees::new();
+    let mut stakes: HashMap<(u8, u8), u64> = HashMap::new();
+    for &op in ops {
+        match op {
+            Op::CreateTree { key, k } => {
+                let expected = if k < 2 {
+                    Err(SortitionError::InvalidK)
+                } else if trees.tree(&key).is_ok() {
+                    Err(SortitionError::TreeAlreadyExists)
+                } else {
+                    Ok(())
+                };
+                assert_eq!(trees.create_tree(key, k as usize), expected);
+            }
+            Op::Set { key, id, value } => {
+                let before = trees.clone();
+                let Ok(tree) = trees.tree(&key) else {
+                    assert_eq!(
+                        trees.set(&key, value, id),
+                        Err(SortitionError::TreeNotFound)
+                    );
+                    continue;
+                };
+                let current = stakes.get(&(key, id)).copied().unwrap_or(0);
+                let total = tree.total() as u128 - current as u128 + value as u128;
+                match trees.set(&key, value, id) {
+                    Ok(()) => {
+                        assert!(total <= u64::MAX as u128);
+                        if value == 0 {
+                            stakes.remove(&(key, id));
+                        } else {
+                            stakes.insert((key, id), value);
+                        }
+                    }
+                    Err(error) => {
+                        assert_eq!(error, SortitionError::Overflow);
+                        assert!(total > u64::MAX as u128);
+                        assert_eq!(trees, before, "a failed set must leave the trees untouched");
+                    }
+                }
+            }
+            Op::Draw { key, number } => match trees.draw(&key, number) {
+                Ok(id) => assert!(stakes.contains_key(&(key, id))),
+                Err(SortitionError::EmptyTree) => {
+                    assert!(!stakes.keys().any(|&(other, _)| other == key))
+                }
+                Err(error) => assert_eq!(error, SortitionError::TreeNotFound),
+            },
+            Op::StakeOf { key, id } => {
+                if trees.tree(&key).is_ok() {
+                    let expected = stakes.get(&(key, id)).copied().unwrap_or(0);
+                    assert_eq!(trees.stake_of(&key, &id), Ok(expected));
+                }
+            }
+            Op::QueryLeaves { key, cursor, count } => {
+                if let Ok(page) = trees.query_leaves(&key, cursor as usize, count as usize) {
+                    assert!(page.entries.len() <= count as usize);
+                    for (id, value) in page.entries {
+                        assert_eq!(stakes.get(&(key, id)), Some(&value));
+                    }
+                }
+            }
+        }
+        for key in 0..4 {
+            if let Ok(violations) = trees.validate(&key) {
+                assert!(violations.is_empty(), "{violations:?}");
+                let total: u64 = stakes
+                    .iter()
+                    .filter(|&(&(other, _), _)| other == key)
+                    .map(|(_, &value)| value)
+                    .sum();
+                assert_eq!(trees.tree(&key).unwrap().total(), total);
+            }
+        }
+    }
+    trees
+}
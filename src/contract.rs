@@ -0,0 +1,144 @@
+//! A NEAR contract exposing [`SortitionSumTrees`] keyed by strings, with
+//! account IDs as participants and `u128` stakes.
+//!
+//! Mutations are restricted to the owner set at initialization. Stakes and
+//! drawn numbers cross the JSON boundary as [`U128`] strings, since JSON
+//! numbers cannot hold a `u128`. Methods return a [`SortitionError`], which
+//! fails the call with its message on chain.
+//!
+//! Each tree is a [`LookupMapStore`], so a call only loads the nodes on the
+//! path it walks instead of deserializing every tree.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId, BorshStorageKey, PanicOnDefault};
+
+use crate::error::SortitionError;
+use crate::lookup_map_store::LookupMapStore;
+use crate::store::NodeStore;
+
+#[derive(BorshSerialize, BorshStorageKey)]
+enum StorageKey {
+    Trees,
+    Tree { key: String },
+}
+
+/// A page of leaves, as returned by [`SortitionContract::query_leaves`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LeavesView {
+    pub start_index: U64,
+    pub entries: Vec<(AccountId, U128)>,
+    pub next_cursor: Option<U64>,
+}
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct SortitionContract {
+    owner_id: AccountId,
+    trees: LookupMap<String, LookupMapStore<AccountId, u128>>,
+}
+
+#[near_bindgen]
+impl SortitionContract {
+    #[init]
+    pub fn new(owner_id: AccountId) -> Self {
+        require!(!env::state_exists(), "Already initialized");
+        Self {
+            owner_id,
+            trees: LookupMap::new(StorageKey::Trees),
+        }
+    }
+
+    pub fn owner_id(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    /// Creates a tree whose nodes have at most `k` children. Owner only.
+    #[handle_result]
+    pub fn create_tree(&mut self, key: String, k: U64) -> Result<(), SortitionError> {
+        self.assert_owner();
+        let k = usize::try_from(k.0).map_err(|_| SortitionError::InvalidK)?;
+        if k < 2 {
+            return Err(SortitionError::InvalidK);
+        }
+        if self.trees.contains_key(&key) {
+            return Err(SortitionError::TreeAlreadyExists);
+        }
+        let tree = LookupMapStore::new(StorageKey::Tree { key: key.clone() }, k)?;
+        self.trees.insert(&key, &tree);
+        Ok(())
+    }
+
+    /// Sets the stake of `id` in a tree; a stake of 0 removes it. Owner only.
+    #[handle_result]
+    pub fn set(&mut self, key: String, value: U128, id: AccountId) -> Result<(), SortitionError> {
+        self.assert_owner();
+        let mut tree = self.tree(&key)?;
+        tree.set(value.0, id)?;
+        self.trees.insert(&key, &tree);
+        Ok(())
+    }
+
+    /// Sets the stakes of several accounts in a tree at once, the last stake
+    /// of a repeated account winning. Nothing is written if any fails. Owner
+    /// only.
+    #[handle_result]
+    pub fn set_many(
+        &mut self,
+        key: String,
+        entries: Vec<(AccountId, U128)>,
+    ) -> Result<(), SortitionError> {
+        self.assert_owner();
+        let mut tree = self.tree(&key)?;
+        tree.set_many(entries.into_iter().map(|(id, value)| (id, value.0)))?;
+        self.trees.insert(&key, &tree);
+        Ok(())
+    }
+
+    #[handle_result]
+    pub fn stake_of(&self, key: String, id: AccountId) -> Result<U128, SortitionError> {
+        Ok(U128(self.tree(&key)?.stake_of(&id)))
+    }
+
+    /// Draws an account with a number, reduced modulo the total stake.
+    #[handle_result]
+    pub fn draw(&self, key: String, drawn_number: U128) -> Result<AccountId, SortitionError> {
+        self.tree(&key)?.draw(drawn_number.0)
+    }
+
+    #[handle_result]
+    pub fn query_leaves(
+        &self,
+        key: String,
+        cursor: U64,
+        count: U64,
+    ) -> Result<LeavesView, SortitionError> {
+        let page = self.tree(&key)?.query_leaves(
+            usize::try_from(cursor.0).unwrap_or(usize::MAX),
+            usize::try_from(count.0).unwrap_or(usize::MAX),
+        );
+        Ok(LeavesView {
+            start_index: U64(page.start_index as u64),
+            entries: page
+                .entries
+                .into_iter()
+                .map(|(id, value)| (id, U128(value)))
+                .collect(),
+            next_cursor: page.next_cursor.map(|cursor| U64(cursor as u64)),
+        })
+    }
+
+    fn tree(&self, key: &String) -> Result<LookupMapStore<AccountId, u128>, SortitionError> {
+        self.trees.get(key).ok_or(SortitionError::TreeNotFound)
+    }
+
+    fn assert_owner(&self) {
+        require!(
+            env::predecessor_account_id() == self.owner_id,
+            "Only the owner can modify the trees"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+/// Errors returned by the operations on [`SortitionSumTrees`](crate::SortitionSumTrees).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortitionError {
+    /// No tree was created with the given key.
+    TreeNotFound,
+    /// A tree was already created with the given key.
+    TreeAlreadyExists,
+    /// No checkpoint of the tree has the given ID.
+    SnapshotNotFound,
+    /// The sum of all values in the tree is 0, so nothing can be drawn.
+    EmptyTree,
+    /// `K` must be at least 2.
+    InvalidK,
+    /// Every ID with a non-zero value in the tree is excluded from the draw.
+    NoEligibleId,
+    /// A node sum would exceed the maximum value.
+    Overflow,
+    /// A node sum would drop below 0, which means the tree is inconsistent.
+    Underflow,
+    /// The ID has no value in the tree.
+    IdNotFound,
+    /// The stake of an ID is lower than the amount taken from it.
+    InsufficientStake,
+    /// `begin` was called while a transaction was already open.
+    TransactionInProgress,
+    /// `commit` or `rollback` was called with no transaction open.
+    NoTransaction,
+    /// A Merkle proof does not match the state root it is checked against.
+    InvalidProof,
+}
+
+impl AsRef<str> for SortitionError {
+    fn as_ref(&self) -> &str {
+        match self {
+            SortitionError::TreeNotFound => "tree not found",
+            SortitionError::TreeAlreadyExists => "tree already exists",
+            SortitionError::SnapshotNotFound => "snapshot not found",
+            SortitionError::EmptyTree => "tree is empty",
+            SortitionError::InvalidK => "K must be at least 2",
+            SortitionError::NoEligibleId => "no eligible ID in the tree",
+            SortitionError::Overflow => "node sum overflow",
+            SortitionError::Underflow => "node sum underflow",
+            SortitionError::IdNotFound => "ID not found",
+            SortitionError::InsufficientStake => "insufficient stake",
+            SortitionError::TransactionInProgress => "a transaction is already in progress",
+            SortitionError::NoTransaction => "no transaction in progress",
+            SortitionError::InvalidProof => "invalid proof",
+        }
+    }
+}
+
+impl fmt::Display for SortitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl std::error::Error for SortitionError {}
@@ -0,0 +1,481 @@
+//! Checkpoints of a tree, for drawing against the stakes of the past.
+//!
+//! Once a tree is checkpointed, its nodes and leaf IDs are mirrored in a
+//! persistent vector: a trie whose nodes are shared between versions and
+//! copied, along with their path from the root, only when a version still
+//! refers to them. A checkpoint is then a clone of the root, and each later
+//! write copies at most `log_32(n)` trie nodes, so checkpoints cost memory in
+//! proportion to the changes made after them rather than to the tree size.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::error::SortitionError;
+use crate::journal::Change;
+use crate::sortition_sum_tree::SortitionSumTree;
+use crate::store::draw_leaf;
+use crate::weight::Weight;
+
+/// A checkpoint of a tree, as returned by
+/// [`SortitionSumTrees::checkpoint`](crate::SortitionSumTrees::checkpoint).
+///
+/// Checkpoints of a tree are numbered from 0 in the order they are taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SnapshotId(pub u64);
+
+const BITS: u32 = 5;
+const BRANCHING: usize = 1 << BITS;
+const MASK: usize = BRANCHING - 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TrieNode<T> {
+    Leaf(Vec<T>),
+    Branch(Vec<Arc<TrieNode<T>>>),
+}
+
+/// A vector with cheap clones: clones share their trie nodes until one of
+/// them writes to a node, which then gets copied along with its path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PersistentVec<T> {
+    len: usize,
+    /// The index bits consumed below the root, 0 when the root is a leaf.
+    shift: u32,
+    root: Arc<TrieNode<T>>,
+}
+
+impl<T: Clone> PersistentVec<T> {
+    pub(crate) fn new() -> PersistentVec<T> {
+        PersistentVec {
+            len: 0,
+            shift: 0,
+            root: Arc::new(TrieNode::Leaf(Vec::new())),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        let mut node = &self.root;
+        let mut shift = self.shift;
+        loop {
+            match &**node {
+                TrieNode::Branch(children) => {
+                    node = &children[(index >> shift) & MASK];
+                    shift -= BITS;
+                }
+                TrieNode::Leaf(values) => return values.get(index & MASK),
+            }
+        }
+    }
+
+    /// Changes an existing value in place, copying the trie nodes on its path
+    /// that are shared with another version.
+    pub(crate) fn update(&mut self, index: usize, change: impl FnOnce(&mut T)) {
+        assert!(index < self.len, "index {index} out of bounds");
+        let mut node = Arc::make_mut(&mut self.root);
+        let mut shift = self.shift;
+        loop {
+            match node {
+                TrieNode::Branch(children) => {
+                    node = Arc::make_mut(&mut children[(index >> shift) & MASK]);
+                    shift -= BITS;
+                }
+                TrieNode::Leaf(values) => {
+                    change(&mut values[index & MASK]);
+                    return;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: T) {
+        let index = self.len;
+        if index >> self.shift >= BRANCHING {
+            // The trie is full: the old root becomes the first child of a new one.
+            let old_root = std::mem::replace(&mut self.root, Arc::new(TrieNode::Leaf(Vec::new())));
+            self.root = Arc::new(TrieNode::Branch(vec![old_root]));
+            self.shift += BITS;
+        }
+        let mut node = Arc::make_mut(&mut self.root);
+        let mut shift = self.shift;
+        loop {
+            match node {
+                TrieNode::Branch(children) => {
+                    let child = (index >> shift) & MASK;
+                    shift -= BITS;
+                    if child == children.len() {
+                        children.push(Arc::new(if shift == 0 {
+                            TrieNode::Leaf(Vec::with_capacity(BRANCHING))
+                        } else {
+                            TrieNode::Branch(Vec::new())
+                        }));
+                    }
+                    node = Arc::make_mut(&mut children[child]);
+                }
+                TrieNode::Leaf(values) => {
+                    values.push(value);
+                    break;
+                }
+            }
+        }
+        self.len += 1;
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PersistentVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = PersistentVec::new();
+        for value in iter {
+            vec.push(value);
+        }
+        vec
+    }
+}
+
+/// The value and ID of every node of a tree.
+type Slots<Id, W> = PersistentVec<(W, Option<Id>)>;
+
+/// The leaves of an ID, as `(version, index)` pairs in version order.
+type Leaves = Vec<(u64, Option<usize>)>;
+
+/// How recording the leaf of an ID changed its leaves in a [`History`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum IndexChange {
+    /// A new version was appended, or the ID got its first one.
+    Pushed,
+    /// The leaf of the current version was replaced; holds the previous one.
+    Replaced(Option<usize>),
+}
+
+/// A checkpoint taken out of a [`History`] by `release`, with the ID leaves
+/// only it could see, to put them back on rollback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Released<Id, W> {
+    snapshot: usize,
+    slots: Slots<Id, W>,
+    indexes: Vec<(Id, Leaves)>,
+}
+
+/// The mirror and checkpoint count of a [`History`] at some point, to go
+/// back to it. The mirror shares its trie nodes, so this is `O(1)` to take.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HistoryMark<Id, W> {
+    slots: Slots<Id, W>,
+    checkpoints: usize,
+}
+
+/// The checkpoints of a tree, and the mirror of its current state that the
+/// next checkpoint will share.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct History<Id, W>
+where
+    Id: Hash + Eq,
+{
+    /// The value and ID of every node of the current tree.
+    slots: Slots<Id, W>,
+    /// The leaves of every ID with a value now or at a live checkpoint, where
+    /// `None` means the ID had no value. Changes made after checkpoint `n`
+    /// have version `n + 1`.
+    indexes: HashMap<Id, Leaves>,
+    /// The checkpoints by ID, `None` once released.
+    checkpoints: Vec<Option<Slots<Id, W>>>,
+}
+
+impl<Id, W> History<Id, W>
+where
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    /**
+     *  @dev Start mirroring a tree. This copies every node once; later writes are mirrored one by one.
+     *  @param _nodes The nodes of the tree.
+     *  @param _ids The IDs of the tree and their leaves.
+     */
+    pub(crate) fn new<'a>(nodes: &[W], ids: impl Iterator<Item = (&'a Id, usize)>) -> History<Id, W>
+    where
+        Id: 'a,
+    {
+        let mut slots: Vec<(W, Option<Id>)> = nodes.iter().map(|&value| (value, None)).collect();
+        let mut indexes = HashMap::new();
+        for (id, index) in ids {
+            slots[index].1 = Some(id.clone());
+            indexes.insert(id.clone(), vec![(0, Some(index))]);
+        }
+        History {
+            slots: slots.into_iter().collect(),
+            indexes,
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /**
+     *  @dev Freeze the current state of the tree.
+     *  @return snapshot The ID of the new checkpoint.
+     *  `O(1)`.
+     */
+    pub(crate) fn checkpoint(&mut self) -> SnapshotId {
+        self.checkpoints.push(Some(self.slots.clone()));
+        SnapshotId(self.checkpoints.len() as u64 - 1)
+    }
+
+    /**
+     *  @dev Drop a checkpoint, along with the leaves of IDs that no other checkpoint can see.
+     *  @param _snapshot The checkpoint.
+     *  @return released What `unrelease` needs to put the checkpoint back. Fails with `SnapshotNotFound` if the checkpoint does not exist or was already released.
+     *  `O(m * log(c))` where
+     *  `m` is the number of ID leaves recorded,
+     *   and `c` is the number of checkpoints.
+     */
+    pub(crate) fn release(
+        &mut self,
+        snapshot: SnapshotId,
+    ) -> Result<Released<Id, W>, SortitionError> {
+        let index = usize::try_from(snapshot.0)
+            .ok()
+            .filter(|&index| index < self.checkpoints.len())
+            .ok_or(SortitionError::SnapshotNotFound)?;
+        let slots = self.checkpoints[index]
+            .take()
+            .ok_or(SortitionError::SnapshotNotFound)?;
+        let live: Vec<u64> = (0..self.checkpoints.len() as u64)
+            .filter(|&version| self.checkpoints[version as usize].is_some())
+            .collect();
+        // A leaf is seen by the checkpoints from its version up to the next
+        // change; the last one is kept for the checkpoints still to come.
+        let seen = |changes: &[(u64, Option<usize>)], i: usize| {
+            let first = live.partition_point(|&version| version < changes[i].0);
+            i + 1 == changes.len()
+                || live
+                    .get(first)
+                    .is_some_and(|&version| version < changes[i + 1].0)
+        };
+        let mut indexes = Vec::new();
+        self.indexes.retain(|id, changes| {
+            if (0..changes.len()).all(|i| seen(changes, i)) {
+                return true;
+            }
+            let kept: Leaves = (0..changes.len())
+                .filter(|&i| seen(changes, i))
+                .map(|i| changes[i])
+                .collect();
+            indexes.push((id.clone(), std::mem::replace(changes, kept)));
+            // An ID without a leaf reads as 0 with or without its changes.
+            !matches!(changes[..], [(_, None)])
+        });
+        Ok(Released {
+            snapshot: index,
+            slots,
+            indexes,
+        })
+    }
+
+    /// Puts back a checkpoint dropped by `release`, once the changes made
+    /// after it are undone.
+    pub(crate) fn unrelease(&mut self, released: Released<Id, W>) {
+        self.checkpoints[released.snapshot] = Some(released.slots);
+        self.indexes.extend(released.indexes);
+    }
+
+    pub(crate) fn set_node(&mut self, index: usize, value: W) {
+        self.slots.update(index, |slot| slot.0 = value);
+    }
+
+    pub(crate) fn push_node(&mut self, value: W) {
+        self.slots.push((value, None));
+    }
+
+    pub(crate) fn link(&mut self, index: usize, id: &Id) -> IndexChange {
+        self.slots.update(index, |slot| slot.1 = Some(id.clone()));
+        self.record_index(id, Some(index))
+    }
+
+    pub(crate) fn unlink(&mut self, index: usize, id: &Id) -> IndexChange {
+        self.slots.update(index, |slot| slot.1 = None);
+        self.record_index(id, None)
+    }
+
+    /// Only the last change of a version is kept, as no checkpoint can see
+    /// the ones before it.
+    fn record_index(&mut self, id: &Id, index: Option<usize>) -> IndexChange {
+        let version = self.checkpoints.len() as u64;
+        match self.indexes.get_mut(id) {
+            Some(changes) => match changes.last_mut() {
+                Some(last) if last.0 == version => {
+                    IndexChange::Replaced(std::mem::replace(&mut last.1, index))
+                }
+                _ => {
+                    changes.push((version, index));
+                    IndexChange::Pushed
+                }
+            },
+            None => {
+                self.indexes.insert(id.clone(), vec![(version, index)]);
+                IndexChange::Pushed
+            }
+        }
+    }
+
+    /// Undoes a change returned by `link` or `unlink`. Changes must be undone
+    /// latest first.
+    pub(crate) fn undo_index(&mut self, id: &Id, change: IndexChange) {
+        let Some(changes) = self.indexes.get_mut(id) else {
+            return;
+        };
+        match change {
+            IndexChange::Pushed => {
+                changes.pop();
+                if changes.is_empty() {
+                    self.indexes.remove(id);
+                }
+            }
+            IndexChange::Replaced(previous) => {
+                if let Some(last) = changes.last_mut() {
+                    last.1 = previous;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn mark(&self) -> HistoryMark<Id, W> {
+        HistoryMark {
+            slots: self.slots.clone(),
+            checkpoints: self.checkpoints.len(),
+        }
+    }
+
+    /// Goes back to a mark, dropping the checkpoints taken after it. The
+    /// indexes are left alone: they are restored with `undo_index`.
+    pub(crate) fn restore(&mut self, mark: HistoryMark<Id, W>) {
+        self.slots = mark.slots;
+        self.checkpoints.truncate(mark.checkpoints);
+    }
+
+    fn snapshot(&self, snapshot: SnapshotId) -> Result<&Slots<Id, W>, SortitionError> {
+        usize::try_from(snapshot.0)
+            .ok()
+            .and_then(|index| self.checkpoints.get(index))
+            .and_then(Option::as_ref)
+            .ok_or(SortitionError::SnapshotNotFound)
+    }
+
+    /**
+     *  @dev Gets the value an ID had at a checkpoint.
+     *  @param _snapshot The checkpoint.
+     *  @param _id The ID of the value.
+     *  @return value The associated value.
+     *  `O(log(c) + log_32(n))` where
+     *  `c` is the number of checkpoints at which the ID changed leaf,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    pub(crate) fn stake_of_at(&self, snapshot: SnapshotId, id: &Id) -> Result<W, SortitionError> {
+        let slots = self.snapshot(snapshot)?;
+        let index = self.indexes.get(id).and_then(|changes| {
+            let seen = changes.partition_point(|&(version, _)| version <= snapshot.0);
+            seen.checked_sub(1).and_then(|last| changes[last].1)
+        });
+        Ok(index
+            .and_then(|index| slots.get(index))
+            .map_or_else(W::zero, |&(value, _)| value))
+    }
+
+    /**
+     *  @dev Draw an ID from the tree as it was at a checkpoint.
+     *  @param _k The max number of children for each node in the tree.
+     *  @param _snapshot The checkpoint.
+     *  @param _drawn_number The drawn number.
+     *  @return ID The drawn ID.
+     *  `O(k * log_k(n) * log_32(n))` where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    pub(crate) fn draw_at(
+        &self,
+        k: usize,
+        snapshot: SnapshotId,
+        drawn_number: W,
+    ) -> Result<Id, SortitionError> {
+        let slots = self.snapshot(snapshot)?;
+        let value = |index: usize| slots.get(index).map_or_else(W::zero, |&(value, _)| value);
+        let leaf_index = draw_leaf(k, slots.len(), value, drawn_number)?;
+        Ok(slots
+            .get(leaf_index)
+            .and_then(|(_, id)| id.clone())
+            .expect("a drawn leaf with a non-zero value is labelled"))
+    }
+}
+
+impl<Id, W> SortitionSumTree<Id, W>
+where
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    /**
+     *  @dev Freeze the current values of the tree, to draw against them later while the tree keeps changing.
+     *  The first checkpoint copies the tree once; every later one is `O(1)`, and each write after it copies `O(log_32(n))` shared trie nodes at most.
+     *  Checkpoints live in memory only: serialized trees do not include them.
+     *  @return snapshot The ID of the new checkpoint.
+     */
+    pub fn checkpoint(&mut self) -> SnapshotId {
+        if let Some(journal) = &mut self.journal {
+            journal.save_history(&self.history);
+        }
+        let nodes = &self.nodes;
+        let ids = &self.ids_to_node_indexes;
+        self.history
+            .get_or_insert_with(|| History::new(nodes, ids.iter().map(|(id, &index)| (id, index))))
+            .checkpoint()
+    }
+
+    /**
+     *  @dev Drop a checkpoint, freeing the nodes and ID leaves that only it could see. Its ID is not reused.
+     *  The tree keeps mirroring its writes for the checkpoints still to come.
+     *  @param _snapshot The checkpoint, as returned by `checkpoint`.
+     *  Fails with `SnapshotNotFound` if the checkpoint does not exist or was already released.
+     *  `O(m * log(c))` where
+     *  `m` is the number of times IDs changed leaf across checkpoints,
+     *   and `c` is the number of checkpoints.
+     */
+    pub fn release_checkpoint(&mut self, snapshot: SnapshotId) -> Result<(), SortitionError> {
+        let released = self
+            .history
+            .as_mut()
+            .ok_or(SortitionError::SnapshotNotFound)?
+            .release(snapshot)?;
+        if let Some(journal) = &mut self.journal {
+            journal.record(Change::Release(released));
+        }
+        Ok(())
+    }
+
+    /**
+     *  @dev Gets the value an ID had at a checkpoint.
+     *  @param _snapshot The checkpoint, as returned by `checkpoint`.
+     *  @param _id The ID of the value.
+     *  @return value The associated value.
+     */
+    pub fn stake_of_at(&self, snapshot: SnapshotId, id: &Id) -> Result<W, SortitionError> {
+        self.history
+            .as_ref()
+            .ok_or(SortitionError::SnapshotNotFound)?
+            .stake_of_at(snapshot, id)
+    }
+
+    /**
+     *  @dev Draw an ID from the tree as it was at a checkpoint. Note that this function fails with `EmptyTree` if the sum of all values in the tree was 0.
+     *  @param _snapshot The checkpoint, as returned by `checkpoint`.
+     *  @param _drawn_number The drawn number.
+     *  @return ID The drawn ID.
+     */
+    pub fn draw_at(&self, snapshot: SnapshotId, drawn_number: W) -> Result<Id, SortitionError> {
+        self.history
+            .as_ref()
+            .ok_or(SortitionError::SnapshotNotFound)?
+            .draw_at(self.k, snapshot, drawn_number)
+    }
+}
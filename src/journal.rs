@@ -0,0 +1,243 @@
+//! The undo log behind [`SortitionSumTrees::begin`](crate::SortitionSumTrees::begin).
+//!
+//! While a transaction is open, every tree records each primitive write of
+//! the `NodeStore` it implements, along with what it overwrote. Rolling back
+//! undoes them in reverse order, which restores the nodes, the stack and the
+//! ID maps exactly, so that later insertions reuse the same vacant leaves as
+//! if the transaction never happened.
+
+use std::hash::Hash;
+
+use crate::error::SortitionError;
+use crate::history::{History, HistoryMark, IndexChange, Released};
+use crate::observer::SortitionObserver;
+use crate::sortition_sum_tree::{SortitionSumTree, SortitionSumTrees};
+use crate::weight::Weight;
+
+/// A write to a tree, with what is needed to undo it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Change<Id, W> {
+    SetNode {
+        index: usize,
+        previous: W,
+    },
+    PushNode,
+    PushVacant,
+    PopVacant {
+        index: usize,
+    },
+    Link {
+        index: usize,
+    },
+    Unlink {
+        index: usize,
+        id: Id,
+    },
+    /// A change to the leaves the history of the tree keeps for an ID.
+    Index {
+        id: Id,
+        change: IndexChange,
+    },
+    /// A released checkpoint.
+    Release(Released<Id, W>),
+}
+
+/// The writes made to a tree since the transaction began.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Journal<Id, W>
+where
+    Id: Hash + Eq,
+{
+    /// Whether the tree was created in the transaction, and goes away with it.
+    created: bool,
+    changes: Vec<Change<Id, W>>,
+    /// The mark of the history before the first write that changed it,
+    /// `Some(None)` if the tree had no history then.
+    history: Option<Option<HistoryMark<Id, W>>>,
+}
+
+impl<Id, W> Journal<Id, W>
+where
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    pub(crate) fn new(created: bool) -> Journal<Id, W> {
+        Journal {
+            created,
+            changes: Vec::new(),
+            history: None,
+        }
+    }
+
+    pub(crate) fn record(&mut self, change: Change<Id, W>) {
+        self.changes.push(change);
+    }
+
+    /// Marks the history before it first changes, or records that there was
+    /// none. The ID index of the history is not copied: its changes are
+    /// recorded one by one as `Change::Index`.
+    pub(crate) fn save_history(&mut self, history: &Option<History<Id, W>>) {
+        if self.history.is_none() {
+            self.history = Some(history.as_ref().map(History::mark));
+        }
+    }
+}
+
+impl<Id, W> SortitionSumTree<Id, W>
+where
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    /**
+     *  @dev Undo every write recorded since the transaction began, latest first, and stop recording.
+     *  `O(m)` where
+     *  `m` is the number of writes recorded.
+     */
+    fn undo(&mut self) {
+        let Some(journal) = self.journal.take() else {
+            return;
+        };
+        for change in journal.changes.into_iter().rev() {
+            let index = match change {
+                Change::SetNode { index, previous } => {
+                    self.nodes[index] = previous;
+                    index
+                }
+                Change::PushNode => {
+                    self.nodes.pop();
+                    continue;
+                }
+                Change::PushVacant => {
+                    self.stack.pop();
+                    continue;
+                }
+                Change::PopVacant { index } => {
+                    self.stack.push(index);
+                    continue;
+                }
+                Change::Link { index } => {
+                    if let Some(id) = self.node_indexes_to_ids.remove(&index) {
+                        self.ids_to_node_indexes.remove(&id);
+                    }
+                    index
+                }
+                Change::Unlink { index, id } => {
+                    self.ids_to_node_indexes.insert(id.clone(), index);
+                    self.node_indexes_to_ids.insert(index, id);
+                    index
+                }
+                Change::Index { id, change } => {
+                    if let Some(history) = &mut self.history {
+                        history.undo_index(&id, change);
+                    }
+                    continue;
+                }
+                Change::Release(released) => {
+                    if let Some(history) = &mut self.history {
+                        history.unrelease(released);
+                    }
+                    continue;
+                }
+            };
+            if let Some(hashes) = &mut self.hashes {
+                hashes.touch(index);
+            }
+        }
+        if let Some(mark) = journal.history {
+            self.history = match (self.history.take(), mark) {
+                (Some(mut history), Some(mark)) => {
+                    history.restore(mark);
+                    Some(history)
+                }
+                _ => None,
+            };
+        }
+    }
+}
+
+impl<Key, Id, W, O> SortitionSumTrees<Key, Id, W, O>
+where
+    Key: Hash + Eq,
+    Id: Hash + Eq + Clone,
+    W: Weight,
+    O: SortitionObserver<Key, Id, W>,
+{
+    /**
+     *  @dev Start recording every write to the trees, so that `rollback` can undo them. Transactions do not nest.
+     *  Fails with `TransactionInProgress` if a transaction is already open.
+     *  `O(t)` where
+     *  `t` is the number of trees.
+     */
+    pub fn begin(&mut self) -> Result<(), SortitionError> {
+        if self.in_transaction {
+            return Err(SortitionError::TransactionInProgress);
+        }
+        for tree in self.sortition_sum_trees.values_mut() {
+            tree.journal = Some(Journal::new(false));
+        }
+        self.in_transaction = true;
+        self.observer.on_begin();
+        Ok(())
+    }
+
+    /**
+     *  @dev Keep every write made since `begin`, and stop recording.
+     *  Fails with `NoTransaction` if no transaction is open.
+     *  `O(t)` where
+     *  `t` is the number of trees.
+     */
+    pub fn commit(&mut self) -> Result<(), SortitionError> {
+        if !self.in_transaction {
+            return Err(SortitionError::NoTransaction);
+        }
+        for tree in self.sortition_sum_trees.values_mut() {
+            tree.journal = None;
+        }
+        self.in_transaction = false;
+        self.observer.on_commit();
+        Ok(())
+    }
+
+    /**
+     *  @dev Undo every write made since `begin`, including the creation of trees, and stop recording.
+     *  Fails with `NoTransaction` if no transaction is open.
+     *  `O(t + m)` where
+     *  `t` is the number of trees,
+     *   and `m` is the number of node, stack and ID writes made since `begin`.
+     */
+    pub fn rollback(&mut self) -> Result<(), SortitionError> {
+        if !self.in_transaction {
+            return Err(SortitionError::NoTransaction);
+        }
+        self.sortition_sum_trees
+            .retain(|_, tree| !tree.journal.as_ref().is_some_and(|journal| journal.created));
+        for tree in self.sortition_sum_trees.values_mut() {
+            tree.undo();
+        }
+        self.in_transaction = false;
+        self.observer.on_rollback();
+        Ok(())
+    }
+
+    /**
+     *  @dev Run a closure in a transaction: its writes are kept if it returns `Ok`, and rolled back if it returns `Err`.
+     *  @param _f The closure, given the trees.
+     *  @return value What the closure returned. Fails with `TransactionInProgress` if a transaction is already open.
+     */
+    pub fn transaction<T, E>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, E>) -> Result<T, E>
+    where
+        E: From<SortitionError>,
+    {
+        self.begin()?;
+        match f(self) {
+            Ok(value) => {
+                self.commit()?;
+                Ok(value)
+            }
+            Err(error) => {
+                self.rollback()?;
+                Err(error)
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! A Rust port of the Kleros `SortitionSumTreeFactory`: K-ary sum trees for
+//! saving weighted IDs and drawing them with probability proportional to
+//! their weight.
+//!
+//! Trees are generic over their key, the ID type of their participants and
+//! the [`Weight`] stored in their nodes.
+
+#[cfg(feature = "near")]
+pub mod contract;
+mod error;
+mod history;
+mod journal;
+#[cfg(feature = "near")]
+mod lookup_map_store;
+mod merkle;
+mod observer;
+mod random;
+mod seed;
+#[cfg(any(feature = "serde", feature = "borsh"))]
+mod serialization;
+mod sortition_sum_tree;
+mod store;
+mod validation;
+mod weight;
+
+pub use error::SortitionError;
+pub use history::SnapshotId;
+#[cfg(feature = "near")]
+pub use lookup_map_store::LookupMapStore;
+pub use merkle::{
+    verify_draw_proof, verify_stake_proof, DrawProof, IdBytes, ProofLevel, StakeProof,
+};
+pub use observer::SortitionObserver;
+pub use random::RandomSource;
+pub use seed::{drawn_number, keccak256};
+pub use sortition_sum_tree::{
+    LeavesPage, SortitionSumTree, SortitionSumTrees, TypeAddress, TypeKey,
+};
+pub use store::NodeStore;
+pub use validation::Violation;
+pub use weight::{Weight, U256};
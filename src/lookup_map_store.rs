@@ -0,0 +1,127 @@
+//! A [`NodeStore`] kept in NEAR contract storage.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, Vector};
+use near_sdk::IntoStorageKey;
+
+use crate::error::SortitionError;
+use crate::store::raw::RawNodeStore;
+use crate::store::NodeStore;
+use crate::weight::Weight;
+
+/// A tree whose nodes, stack and IDs each live under their own storage
+/// prefix, so that only the entries a call touches are read or written.
+///
+/// The struct itself only holds `k`, the node count and the prefixes: store
+/// it in the contract state (or in a collection, like any nested near-sdk
+/// collection) and write it back after a mutation.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LookupMapStore<Id, V>
+where
+    Id: BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize,
+{
+    k: u64,
+    node_count: u64,
+    nodes: LookupMap<u64, V>,
+    stack: Vector<u64>,
+    ids_to_node_indexes: LookupMap<Id, u64>,
+    node_indexes_to_ids: LookupMap<u64, Id>,
+}
+
+impl<Id, V> LookupMapStore<Id, V>
+where
+    Id: BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize + Weight,
+{
+    /**
+     *  @dev Create a tree holding only its root, writing the root to storage.
+     *  @param _prefix The storage prefix of the tree, which must not be a prefix of any other key of the contract.
+     *  @param _k The max number of children for each node in the tree. Fails with `InvalidK`, writing nothing, if it is below 2.
+     */
+    pub fn new<S: IntoStorageKey>(prefix: S, k: usize) -> Result<Self, SortitionError> {
+        if k < 2 {
+            return Err(SortitionError::InvalidK);
+        }
+        let prefix = prefix.into_storage_key();
+        let with_suffix = |suffix: u8| [prefix.as_slice(), &[suffix]].concat();
+        let mut store = LookupMapStore {
+            k: k as u64,
+            node_count: 0,
+            nodes: LookupMap::new(with_suffix(b'n')),
+            stack: Vector::new(with_suffix(b's')),
+            ids_to_node_indexes: LookupMap::new(with_suffix(b'i')),
+            node_indexes_to_ids: LookupMap::new(with_suffix(b'x')),
+        };
+        store.push_node(V::zero());
+        Ok(store)
+    }
+}
+
+impl<Id, V> NodeStore<Id, V> for LookupMapStore<Id, V>
+where
+    Id: BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize + Weight,
+{
+    fn k(&self) -> usize {
+        self.k as usize
+    }
+
+    fn node_count(&self) -> usize {
+        self.node_count as usize
+    }
+
+    fn node(&self, index: usize) -> V {
+        self.nodes.get(&(index as u64)).unwrap_or_else(V::zero)
+    }
+
+    fn last_vacant(&self) -> Option<usize> {
+        self.stack
+            .len()
+            .checked_sub(1)
+            .and_then(|last| self.stack.get(last))
+            .map(|index| index as usize)
+    }
+
+    fn index_of(&self, id: &Id) -> Option<usize> {
+        self.ids_to_node_indexes.get(id).map(|index| index as usize)
+    }
+
+    fn id_of(&self, index: usize) -> Option<Id> {
+        self.node_indexes_to_ids.get(&(index as u64))
+    }
+}
+
+impl<Id, V> RawNodeStore<Id, V> for LookupMapStore<Id, V>
+where
+    Id: BorshSerialize + BorshDeserialize,
+    V: BorshSerialize + BorshDeserialize + Weight,
+{
+    fn set_node(&mut self, index: usize, value: V) {
+        self.nodes.insert(&(index as u64), &value);
+    }
+
+    fn push_node(&mut self, value: V) {
+        self.nodes.insert(&self.node_count, &value);
+        self.node_count += 1;
+    }
+
+    fn pop_vacant(&mut self) -> Option<usize> {
+        self.stack.pop().map(|index| index as usize)
+    }
+
+    fn push_vacant(&mut self, index: usize) {
+        self.stack.push(&(index as u64));
+    }
+
+    fn link(&mut self, index: usize, id: Id) {
+        self.ids_to_node_indexes.insert(&id, &(index as u64));
+        self.node_indexes_to_ids.insert(&(index as u64), &id);
+    }
+
+    fn unlink(&mut self, index: usize) -> Option<Id> {
+        let id = self.node_indexes_to_ids.remove(&(index as u64))?;
+        self.ids_to_node_indexes.remove(&id);
+        Some(id)
+    }
+}
@@ -0,0 +1,392 @@
+//! A Merkle hash layer over the nodes of a tree, so that draws and stakes
+//! can be checked against a 32-byte state root without the tree.
+//!
+//! Every node commits to its value and to what is below it, with Solidity's
+//! `keccak256` and its `abi.encodePacked` layout:
+//!
+//! - a labelled leaf hashes `0x00 ++ uint256(value) ++ id_bytes(id)`,
+//! - a vacant leaf hashes `0x01`,
+//! - an internal node hashes `0x02` followed by `bytes32(hash) ++
+//!   uint256(value)` for each of its children, in order.
+//!
+//! The state root is the hash of the root node. Hashes are computed on
+//! demand: the first `state_root` of a tree hashes all of it, and later ones
+//! only rehash the nodes written since, along with their parents.
+
+use std::collections::BTreeSet;
+use std::hash::Hash;
+
+use crate::error::SortitionError;
+use crate::observer::SortitionObserver;
+use crate::seed::keccak256;
+use crate::sortition_sum_tree::{SortitionSumTree, SortitionSumTrees};
+use crate::store::{affected_ancestors, NodeStore};
+use crate::weight::Weight;
+
+const LEAF: u8 = 0x00;
+const VACANT: u8 = 0x01;
+const INTERNAL: u8 = 0x02;
+
+/// The bytes an ID is hashed as in its leaf.
+pub trait IdBytes {
+    fn id_bytes(&self) -> Vec<u8>;
+}
+
+macro_rules! impl_id_bytes {
+    ($($t:ty),*) => {
+        $(
+            /// Big-endian, like a Solidity `uint` of the same width.
+            impl IdBytes for $t {
+                fn id_bytes(&self) -> Vec<u8> {
+                    self.to_be_bytes().to_vec()
+                }
+            }
+        )*
+    };
+}
+
+impl_id_bytes!(u8, u16, u32, u64, u128);
+
+impl IdBytes for [u8; 32] {
+    fn id_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+}
+
+impl IdBytes for Vec<u8> {
+    fn id_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+impl IdBytes for String {
+    fn id_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+#[cfg(feature = "near")]
+impl IdBytes for near_sdk::AccountId {
+    fn id_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+/// One node on the path of a proof: the hashes and values of all its
+/// children, and which of them the path goes down to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofLevel<W> {
+    pub children: Vec<([u8; 32], W)>,
+    pub position: usize,
+}
+
+/// The path of a draw from the root to the drawn leaf, as returned by
+/// [`SortitionSumTrees::draw_with_proof`](crate::SortitionSumTrees::draw_with_proof).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrawProof<Id, W> {
+    /// The drawn ID.
+    pub id: Id,
+    /// The internal nodes from the root down to the parent of the drawn leaf.
+    pub levels: Vec<ProofLevel<W>>,
+}
+
+/// The path from the root to the leaf of an ID, as returned by
+/// [`SortitionSumTrees::prove_stake`](crate::SortitionSumTrees::prove_stake).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StakeProof<W> {
+    /// The internal nodes from the root down to the parent of the leaf.
+    pub levels: Vec<ProofLevel<W>>,
+}
+
+/// The hashes of the nodes of a tree, and the nodes written since they were
+/// last computed.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HashLayer {
+    hashes: Vec<[u8; 32]>,
+    dirty: BTreeSet<usize>,
+}
+
+impl HashLayer {
+    /// Marks a node, and through it its parents, for rehashing.
+    pub(crate) fn touch(&mut self, index: usize) {
+        self.dirty.insert(index);
+    }
+}
+
+pub(crate) fn leaf_hash<Id: IdBytes, W: Weight>(id: &Id, value: W) -> [u8; 32] {
+    let mut packed = vec![LEAF];
+    packed.extend(uint256(value));
+    packed.extend(id.id_bytes());
+    keccak256(&packed)
+}
+
+pub(crate) fn internal_hash<W: Weight>(children: &[([u8; 32], W)]) -> [u8; 32] {
+    let mut packed = Vec::with_capacity(1 + 64 * children.len());
+    packed.push(INTERNAL);
+    for (hash, value) in children {
+        packed.extend(hash);
+        packed.extend(uint256(*value));
+    }
+    keccak256(&packed)
+}
+
+fn uint256<W: Weight>(value: W) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.into_u256().to_big_endian(&mut bytes);
+    bytes
+}
+
+impl<Id, W> SortitionSumTree<Id, W>
+where
+    Id: Hash + Eq + Clone + IdBytes,
+    W: Weight,
+{
+    /**
+     *  @dev Bring the hashes of the tree up to date: every node written since the last call, and every parent of one, is rehashed from the bottom up.
+     *  `O(m * k * log_k(n))` where
+     *  `m` is the number of nodes written since the last call,
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended. The first call hashes every node.
+     */
+    fn refresh_hashes(&mut self) -> &[[u8; 32]] {
+        let node_count = self.nodes.len();
+        let layer = self.hashes.get_or_insert_with(|| HashLayer {
+            hashes: Vec::new(),
+            dirty: (0..node_count).collect(),
+        });
+        layer.hashes.resize(node_count, [0; 32]);
+        let dirty = std::mem::take(&mut layer.dirty);
+        // A rollback may have removed nodes written since the last call.
+        for index in affected_ancestors(self.k, dirty.range(..node_count).copied()) {
+            let first_child = self.k.saturating_mul(index).saturating_add(1);
+            layer.hashes[index] = if first_child < node_count {
+                let last_child = first_child.saturating_add(self.k).min(node_count);
+                let children: Vec<([u8; 32], W)> = (first_child..last_child)
+                    .map(|child| (layer.hashes[child], self.nodes[child]))
+                    .collect();
+                internal_hash(&children)
+            } else {
+                match self.node_indexes_to_ids.get(&index) {
+                    Some(id) => leaf_hash(id, self.nodes[index]),
+                    None => keccak256(&[VACANT]),
+                }
+            };
+        }
+        &layer.hashes
+    }
+
+    /**
+     *  @dev The Merkle root of the tree, committing to every ID and value in it.
+     *  @return root The hash of the root node.
+     *  Takes `&mut self` to bring the hashes up to date: the first call hashes the whole tree, later ones only the paths written since.
+     */
+    pub fn state_root(&mut self) -> [u8; 32] {
+        self.refresh_hashes()[0]
+    }
+
+    /**
+     *  @dev Draw an ID from the tree using a number, along with the proof that the draw followed the tree of `state_root`. Note that this function fails with `EmptyTree` if the sum of all values in the tree is 0.
+     *  @param _drawn_number The drawn number.
+     *  @return proof The drawn ID and its path, to check with `verify_draw_proof`.
+     *  `O(k * log_k(n))` on top of bringing the hashes up to date, where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    pub fn draw_with_proof(&mut self, drawn_number: W) -> Result<DrawProof<Id, W>, SortitionError> {
+        let id = NodeStore::draw(&*self, drawn_number)?;
+        let leaf_index = self.ids_to_node_indexes[&id];
+        let levels = self.path_to(leaf_index);
+        Ok(DrawProof { id, levels })
+    }
+
+    /**
+     *  @dev Prove the value of an ID against `state_root`. The proof follows the leaf the ID is in now, wherever `set` moved it.
+     *  @param _id The ID of the value.
+     *  @return proof The path to the leaf of the ID, to check with `verify_stake_proof`. Fails with `IdNotFound` if the ID has no value in the tree.
+     *  `O(k * log_k(n))` on top of bringing the hashes up to date, where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    pub fn prove_stake(&mut self, id: &Id) -> Result<StakeProof<W>, SortitionError> {
+        let leaf_index = *self
+            .ids_to_node_indexes
+            .get(id)
+            .ok_or(SortitionError::IdNotFound)?;
+        Ok(StakeProof {
+            levels: self.path_to(leaf_index),
+        })
+    }
+
+    /**
+     *  @dev The internal nodes from the root down to the parent of a node, with the hashes and values of their children.
+     *  @param _index The index of the node.
+     *  @return levels The proof levels, root first.
+     */
+    pub(crate) fn path_to(&mut self, index: usize) -> Vec<ProofLevel<W>> {
+        self.refresh_hashes();
+        let k = self.k;
+        let node_count = self.nodes.len();
+        let hashes = &self.hashes.as_ref().expect("just refreshed").hashes;
+        let mut levels = Vec::new();
+        let mut child = index;
+        while child != 0 {
+            let parent = (child - 1) / k;
+            let first_child = k * parent + 1;
+            let last_child = first_child.saturating_add(k).min(node_count);
+            levels.push(ProofLevel {
+                children: (first_child..last_child)
+                    .map(|sibling| (hashes[sibling], self.nodes[sibling]))
+                    .collect(),
+                position: child - first_child,
+            });
+            child = parent;
+        }
+        levels.reverse();
+        levels
+    }
+}
+
+/**
+ *  @dev Check that a draw followed the tree committed to by a state root, without the tree.
+ *  @param _root The state root of the tree, as returned by `state_root`.
+ *  @param _drawn_number The drawn number the draw was made with.
+ *  @param _proof The proof returned by `draw_with_proof`.
+ *  @return ID The drawn ID. Fails with `InvalidProof` if the proof does not hash to `_root`, if a node's value is not the sum of its children, or if the number leads elsewhere, and with `EmptyTree` if the tree was empty.
+ *  `O(k * log_k(n))` where
+ *  `k` is the maximum number of childs per node in the tree,
+ *   and `n` is the maximum number of nodes ever appended.
+ */
+pub fn verify_draw_proof<Id, W>(
+    root: &[u8; 32],
+    drawn_number: W,
+    proof: &DrawProof<Id, W>,
+) -> Result<Id, SortitionError>
+where
+    Id: IdBytes + Clone,
+    W: Weight,
+{
+    let Some(top) = proof.levels.first() else {
+        // A tree whose root is a leaf holds no value.
+        return Err(SortitionError::EmptyTree);
+    };
+    let total = sum(&top.children)?;
+    if total.is_zero() {
+        return Err(SortitionError::EmptyTree);
+    }
+    let mut current_drawn_number = drawn_number.modulo(total);
+    let mut expected_hash = *root;
+    let mut expected_value = total;
+    for level in &proof.levels {
+        if internal_hash(&level.children) != expected_hash
+            || sum(&level.children)? != expected_value
+        {
+            return Err(SortitionError::InvalidProof);
+        }
+        let mut position = None;
+        for (i, &(_, value)) in level.children.iter().enumerate() {
+            if current_drawn_number >= value {
+                current_drawn_number = current_drawn_number
+                    .checked_sub(value)
+                    .ok_or(SortitionError::Underflow)?;
+            } else {
+                position = Some(i);
+                break;
+            }
+        }
+        if position != Some(level.position) {
+            return Err(SortitionError::InvalidProof);
+        }
+        (expected_hash, expected_value) = level.children[level.position];
+    }
+    if leaf_hash(&proof.id, expected_value) != expected_hash {
+        return Err(SortitionError::InvalidProof);
+    }
+    Ok(proof.id.clone())
+}
+
+/**
+ *  @dev Check that an ID has a value in the tree committed to by a state root, without the tree.
+ *  @param _root The state root of the tree, as returned by `state_root`.
+ *  @param _id The ID of the value.
+ *  @param _stake The value of the ID.
+ *  @param _proof The proof returned by `prove_stake`.
+ *  Fails with `InvalidProof` if the proof does not hash to `_root` from a leaf holding `_stake` for `_id`, or if a node's value is not the sum of its children.
+ *  `O(k * log_k(n))` where
+ *  `k` is the maximum number of childs per node in the tree,
+ *   and `n` is the maximum number of nodes ever appended.
+ */
+pub fn verify_stake_proof<Id, W>(
+    root: &[u8; 32],
+    id: &Id,
+    stake: W,
+    proof: &StakeProof<W>,
+) -> Result<(), SortitionError>
+where
+    Id: IdBytes,
+    W: Weight,
+{
+    if proof.levels.is_empty() {
+        // The root is never labelled.
+        return Err(SortitionError::InvalidProof);
+    }
+    let mut node = (leaf_hash(id, stake), stake);
+    for level in proof.levels.iter().rev() {
+        if level.children.get(level.position) != Some(&node) {
+            return Err(SortitionError::InvalidProof);
+        }
+        node = (internal_hash(&level.children), sum(&level.children)?);
+    }
+    if node.0 != *root {
+        return Err(SortitionError::InvalidProof);
+    }
+    Ok(())
+}
+
+fn sum<W: Weight>(children: &[([u8; 32], W)]) -> Result<W, SortitionError> {
+    children.iter().try_fold(W::zero(), |sum, &(_, value)| {
+        sum.checked_add(value).ok_or(SortitionError::InvalidProof)
+    })
+}
+
+impl<Key, Id, W, O> SortitionSumTrees<Key, Id, W, O>
+where
+    Key: Hash + Eq,
+    Id: Hash + Eq + Clone + IdBytes,
+    W: Weight,
+    O: SortitionObserver<Key, Id, W>,
+{
+    /**
+     *  @dev The Merkle root of a tree. See `SortitionSumTree::state_root`.
+     *  @param _key The key of the tree.
+     *  @return root The hash of the root node.
+     */
+    pub fn state_root(&mut self, key: &Key) -> Result<[u8; 32], SortitionError> {
+        Ok(self.tree_mut(key)?.state_root())
+    }
+
+    /**
+     *  @dev Draw an ID from a tree using a number, along with the proof that the draw followed the tree of `state_root`. Note that this function fails with `EmptyTree` if the sum of all values in the tree is 0.
+     *  @param _key The key of the tree.
+     *  @param _drawn_number The drawn number.
+     *  @return proof The drawn ID and its path, to check with `verify_draw_proof`.
+     */
+    pub fn draw_with_proof(
+        &mut self,
+        key: &Key,
+        drawn_number: W,
+    ) -> Result<DrawProof<Id, W>, SortitionError> {
+        let proof = self.tree_mut(key)?.draw_with_proof(drawn_number)?;
+        self.observer.on_draw(key, &proof.id);
+        Ok(proof)
+    }
+
+    /**
+     *  @dev Prove the value of an ID in a tree against `state_root`. See `SortitionSumTree::prove_stake`.
+     *  @param _key The key of the tree.
+     *  @param _id The ID of the value.
+     *  @return proof The path to the leaf of the ID, to check with `verify_stake_proof`.
+     */
+    pub fn prove_stake(&mut self, key: &Key, id: &Id) -> Result<StakeProof<W>, SortitionError> {
+        self.tree_mut(key)?.prove_stake(id)
+    }
+}
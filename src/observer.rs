@@ -0,0 +1,92 @@
+//! Hooks for following what happens to the trees without diffing their nodes.
+
+/// Receives the events of a [`SortitionSumTrees`](crate::SortitionSumTrees).
+///
+/// Every method does nothing by default, and `()` is the observer of trees
+/// built with `SortitionSumTrees::new`, so unobserved trees pay nothing.
+/// Events are only reported once the operation can no longer fail. The hooks
+/// take `&self` as draws do not mutate the trees: an observer that keeps
+/// state needs interior mutability, such as a `RefCell` or a channel.
+///
+/// Writes made in a transaction are reported as they happen, between
+/// `on_begin` and `on_commit` or `on_rollback`. `rollback` does not report
+/// the writes it undoes: an observer that follows the trees holds the events
+/// of a transaction until `on_commit`, or drops them on `on_rollback`.
+pub trait SortitionObserver<Key, Id, W> {
+    /**
+     *  @dev Called when a tree is created.
+     *  @param _key The key of the new tree.
+     *  @param _k The max number of children for each node in the new tree.
+     */
+    fn on_tree_created(&self, _key: &Key, _k: usize) {}
+
+    /**
+     *  @dev Called when an ID without a value gets one.
+     *  @param _key The key of the tree.
+     *  @param _id The ID of the value.
+     *  @param _value The new value.
+     *  @param _tree_index The index of the leaf holding the value.
+     */
+    fn on_stake_inserted(&self, _key: &Key, _id: &Id, _value: W, _tree_index: usize) {}
+
+    /**
+     *  @dev Called when the value of an ID changes to another non-zero value.
+     *  @param _key The key of the tree.
+     *  @param _id The ID of the value.
+     *  @param _old_value The value before the change.
+     *  @param _new_value The value after the change.
+     *  @param _tree_index The index of the leaf holding the value.
+     */
+    fn on_stake_updated(
+        &self,
+        _key: &Key,
+        _id: &Id,
+        _old_value: W,
+        _new_value: W,
+        _tree_index: usize,
+    ) {
+    }
+
+    /**
+     *  @dev Called when the value of an ID is set to 0, vacating its leaf.
+     *  @param _key The key of the tree.
+     *  @param _id The ID of the value.
+     *  @param _old_value The value before the removal.
+     *  @param _tree_index The index of the vacated leaf.
+     */
+    fn on_stake_removed(&self, _key: &Key, _id: &Id, _old_value: W, _tree_index: usize) {}
+
+    /**
+     *  @dev Called when an insertion appends a first child under a leaf, moving that leaf down. Reported before the insertion itself.
+     *  @param _key The key of the tree.
+     *  @param _id The ID of the moved leaf. Its value is unchanged.
+     *  @param _from_index The index of the leaf before the move, now an internal node.
+     *  @param _to_index The index of the leaf after the move.
+     */
+    fn on_leaf_relocated(&self, _key: &Key, _id: &Id, _from_index: usize, _to_index: usize) {}
+
+    /**
+     *  @dev Called for every ID drawn, whichever draw method was used.
+     *  @param _key The key of the tree.
+     *  @param _id The drawn ID.
+     */
+    fn on_draw(&self, _key: &Key, _id: &Id) {}
+
+    /**
+     *  @dev Called when a transaction begins.
+     */
+    fn on_begin(&self) {}
+
+    /**
+     *  @dev Called when a transaction is committed: the events reported since `on_begin` stand.
+     */
+    fn on_commit(&self) {}
+
+    /**
+     *  @dev Called when a transaction is rolled back: the writes reported since `on_begin`, and the trees created since, are undone.
+     */
+    fn on_rollback(&self) {}
+}
+
+/// The observer of unobserved trees.
+impl<Key, Id, W> SortitionObserver<Key, Id, W> for () {}
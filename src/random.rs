@@ -0,0 +1,47 @@
+use crate::weight::{Weight, U256};
+
+/// A source of uniformly random bytes.
+///
+/// Implemented for any `FnMut(&mut [u8])`, so an RNG can be plugged in with
+/// a closure such as `|buf: &mut [u8]| rng.fill_bytes(buf)`.
+pub trait RandomSource {
+    /// Fills `dest` with random bytes.
+    fn fill_bytes(&mut self, dest: &mut [u8]);
+}
+
+impl<F> RandomSource for F
+where
+    F: FnMut(&mut [u8]),
+{
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self(dest)
+    }
+}
+
+/// Draws a number uniformly from `[0, bound)` by rejection sampling.
+///
+/// Only as many random bits as `bound` has are read per attempt, and
+/// candidates `>= bound` are rejected instead of reduced modulo `bound`, so
+/// the result carries no modulo bias. Each attempt succeeds with probability
+/// above 1/2. `bound` must be non-zero.
+pub(crate) fn uniform_below<W, R>(source: &mut R, bound: W) -> W
+where
+    W: Weight,
+    R: RandomSource + ?Sized,
+{
+    let bound = bound.into_u256();
+    let bits = bound.bits();
+    let len = bits.div_ceil(8);
+    let mut bytes = [0u8; 32];
+    loop {
+        source.fill_bytes(&mut bytes[32 - len..]);
+        if !bits.is_multiple_of(8) {
+            bytes[32 - len] &= (1u8 << (bits % 8)) - 1;
+        }
+        let candidate = U256::from_big_endian(&bytes);
+        if candidate < bound {
+            // `candidate < bound`, and `bound` came from a `W`.
+            return W::from_u256(candidate).expect("candidate is below a W");
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::weight::U256;
+
+/// Solidity's `keccak256`.
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    let mut output = [0u8; 32];
+    hasher.update(data);
+    hasher.finalize(&mut output);
+    output
+}
+
+/// Derives a drawn number the way Kleros court contracts draw jurors:
+/// `uint256(keccak256(abi.encodePacked(RN, disputeID, nonce)))`, as in
+/// `KlerosLiquid.drawJurors` and the v2 `SortitionModule`. The random number
+/// is given as the 32 bytes of the `uint256` RN, and every word is packed as
+/// a `uint256`.
+pub fn drawn_number(seed: &[u8; 32], dispute_id: U256, nonce: u64) -> U256 {
+    let mut packed = [0u8; 96];
+    packed[..32].copy_from_slice(seed);
+    dispute_id.to_big_endian(&mut packed[32..64]);
+    U256::from(nonce).to_big_endian(&mut packed[64..]);
+    U256::from_big_endian(&keccak256(&packed))
+}
@@ -0,0 +1,214 @@
+//! Versioned serde and borsh representations of the trees.
+//!
+//! A tree is stored as `V1 { k, nodes, stack, ids }`, where `ids` lists the
+//! `(node index, ID)` labels in index order and the ID to index map is
+//! rebuilt on load. Indexes are stored as `u64` so that snapshots do not
+//! depend on the platform. Every invariant is checked on load with
+//! [`SortitionSumTree::validate`], so a corrupted snapshot is rejected
+//! instead of producing wrong draws.
+
+use std::hash::Hash;
+
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "serde")]
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::sortition_sum_tree::{SortitionSumTree, SortitionSumTrees};
+use crate::validation::Violation;
+use crate::weight::Weight;
+
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize))]
+enum VersionedTreeRef<'a, Id, V> {
+    V1(TreeV1Ref<'a, Id, V>),
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize))]
+struct TreeV1Ref<'a, Id, V> {
+    k: u64,
+    nodes: &'a [V],
+    stack: Vec<u64>,
+    ids: Vec<(u64, &'a Id)>,
+}
+
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(feature = "borsh", derive(BorshDeserialize))]
+enum VersionedTree<Id, V> {
+    V1(TreeV1<Id, V>),
+}
+
+#[cfg_attr(feature = "serde", derive(Deserialize))]
+#[cfg_attr(feature = "borsh", derive(BorshDeserialize))]
+struct TreeV1<Id, V> {
+    k: u64,
+    nodes: Vec<V>,
+    stack: Vec<u64>,
+    ids: Vec<(u64, Id)>,
+}
+
+fn to_versioned<Id, W>(tree: &SortitionSumTree<Id, W>) -> VersionedTreeRef<'_, Id, W>
+where
+    Id: Hash + Eq,
+{
+    let mut ids: Vec<(u64, &Id)> = tree
+        .node_indexes_to_ids
+        .iter()
+        .map(|(&index, id)| (index as u64, id))
+        .collect();
+    ids.sort_unstable_by_key(|&(index, _)| index);
+    VersionedTreeRef::V1(TreeV1Ref {
+        k: tree.k as u64,
+        nodes: &tree.nodes,
+        stack: tree.stack.iter().map(|&index| index as u64).collect(),
+        ids,
+    })
+}
+
+fn index(value: u64) -> Result<usize, String> {
+    usize::try_from(value).map_err(|_| format!("index {value} does not fit in usize"))
+}
+
+fn from_versioned<Id, W>(versioned: VersionedTree<Id, W>) -> Result<SortitionSumTree<Id, W>, String>
+where
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    let VersionedTree::V1(repr) = versioned;
+    let k = index(repr.k)?;
+    let mut tree =
+        SortitionSumTree::new(k).map_err(|_| Violation::<Id, W>::InvalidK { k }.to_string())?;
+    tree.nodes = repr.nodes;
+    tree.stack = repr
+        .stack
+        .into_iter()
+        .map(index)
+        .collect::<Result<_, _>>()?;
+    for (node_index, id) in repr.ids {
+        let node_index = index(node_index)?;
+        if tree
+            .ids_to_node_indexes
+            .insert(id.clone(), node_index)
+            .is_some()
+        {
+            return Err(format!("the ID at node {node_index} labels several nodes"));
+        }
+        if tree.node_indexes_to_ids.insert(node_index, id).is_some() {
+            return Err(format!("node {node_index} has several IDs"));
+        }
+    }
+    let violations = tree.validate();
+    if !violations.is_empty() {
+        let messages: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        return Err(messages.join("; "));
+    }
+    Ok(tree)
+}
+
+#[cfg(feature = "serde")]
+impl<Id, W> Serialize for SortitionSumTree<Id, W>
+where
+    Id: Serialize + Hash + Eq,
+    W: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Serialize::serialize(&to_versioned(self), serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Id, W> Deserialize<'de> for SortitionSumTree<Id, W>
+where
+    Id: Deserialize<'de> + Hash + Eq + Clone,
+    W: Deserialize<'de> + Weight,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let versioned: VersionedTree<Id, W> = Deserialize::deserialize(deserializer)?;
+        from_versioned(versioned).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Key, Id, W, O> Serialize for SortitionSumTrees<Key, Id, W, O>
+where
+    Key: Serialize + Hash + Eq,
+    Id: Serialize + Hash + Eq,
+    W: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Serialize::serialize(&self.sortition_sum_trees, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Key, Id, W, O> Deserialize<'de> for SortitionSumTrees<Key, Id, W, O>
+where
+    Key: Deserialize<'de> + Hash + Eq,
+    Id: Deserialize<'de> + Hash + Eq + Clone,
+    W: Deserialize<'de> + Weight,
+    O: Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(SortitionSumTrees {
+            sortition_sum_trees: Deserialize::deserialize(deserializer)?,
+            in_transaction: false,
+            observer: O::default(),
+        })
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<Id, W> BorshSerialize for SortitionSumTree<Id, W>
+where
+    Id: BorshSerialize + Hash + Eq,
+    W: BorshSerialize,
+{
+    fn serialize<Writer: std::io::Write>(&self, writer: &mut Writer) -> std::io::Result<()> {
+        BorshSerialize::serialize(&to_versioned(self), writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<Id, W> BorshDeserialize for SortitionSumTree<Id, W>
+where
+    Id: BorshDeserialize + Hash + Eq + Clone,
+    W: BorshDeserialize + Weight,
+{
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        let versioned: VersionedTree<Id, W> = BorshDeserialize::deserialize(buf)?;
+        from_versioned(versioned)
+            .map_err(|message| std::io::Error::new(std::io::ErrorKind::InvalidData, message))
+    }
+}
+
+/// Trees are written in key order, so equal `SortitionSumTrees` always
+/// produce the same bytes.
+#[cfg(feature = "borsh")]
+impl<Key, Id, W, O> BorshSerialize for SortitionSumTrees<Key, Id, W, O>
+where
+    Key: BorshSerialize + PartialOrd + Hash + Eq,
+    Id: BorshSerialize + Hash + Eq,
+    W: BorshSerialize,
+{
+    fn serialize<Writer: std::io::Write>(&self, writer: &mut Writer) -> std::io::Result<()> {
+        BorshSerialize::serialize(&self.sortition_sum_trees, writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<Key, Id, W, O> BorshDeserialize for SortitionSumTrees<Key, Id, W, O>
+where
+    Key: BorshDeserialize + Hash + Eq,
+    Id: BorshDeserialize + Hash + Eq + Clone,
+    W: BorshDeserialize + Weight,
+    O: Default,
+{
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        Ok(SortitionSumTrees {
+            sortition_sum_trees: BorshDeserialize::deserialize(buf)?,
+            in_transaction: false,
+            observer: O::default(),
+        })
+    }
+}
@@ -0,0 +1,808 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, Hash};
+
+use crate::error::SortitionError;
+use crate::history::{History, SnapshotId};
+use crate::journal::Journal;
+use crate::merkle::HashLayer;
+use crate::observer::SortitionObserver;
+use crate::random::{uniform_below, RandomSource};
+use crate::seed::drawn_number;
+use crate::store::{set_many_observed, set_observed, NodeStore};
+use crate::validation::Violation;
+use crate::weight::{Weight, U256};
+
+/// The default ID type, mirroring the `bytes32` IDs of the Solidity library.
+pub type TypeAddress = u128;
+/// The default tree key type, mirroring the `bytes32` keys of the Solidity library.
+pub type TypeKey = u128;
+
+#[derive(Debug, Clone)]
+pub struct SortitionSumTree<Id = TypeAddress, W = u128>
+where
+    Id: Hash + Eq,
+{
+    pub(crate) k: usize,
+    pub(crate) stack: Vec<usize>,
+    pub(crate) nodes: Vec<W>,
+    pub(crate) ids_to_node_indexes: HashMap<Id, usize>,
+    pub(crate) node_indexes_to_ids: HashMap<usize, Id>,
+    /// The checkpoints of the tree, from the first call to `checkpoint` on.
+    pub(crate) history: Option<History<Id, W>>,
+    /// The Merkle hashes of the nodes, from the first call to `state_root` on.
+    pub(crate) hashes: Option<HashLayer>,
+    /// The writes to undo on `rollback`, while a transaction is open.
+    pub(crate) journal: Option<Journal<Id, W>>,
+}
+
+/// Trees are equal when they hold the same nodes, IDs and checkpoints,
+/// whether or not their hashes were computed.
+impl<Id, W> PartialEq for SortitionSumTree<Id, W>
+where
+    Id: Hash + Eq,
+    W: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.k == other.k
+            && self.stack == other.stack
+            && self.nodes == other.nodes
+            && self.ids_to_node_indexes == other.ids_to_node_indexes
+            && self.node_indexes_to_ids == other.node_indexes_to_ids
+            && self.history == other.history
+    }
+}
+
+impl<Id, W> Eq for SortitionSumTree<Id, W>
+where
+    Id: Hash + Eq,
+    W: Eq,
+{
+}
+
+impl<Id, W> SortitionSumTree<Id, W>
+where
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    /**
+     *  @dev Create a tree holding only its root.
+     *  @param _k The max number of children for each node in the tree. Fails with `InvalidK` if it is below 2.
+     */
+    pub fn new(k: usize) -> Result<SortitionSumTree<Id, W>, SortitionError> {
+        if k < 2 {
+            return Err(SortitionError::InvalidK);
+        }
+        Ok(SortitionSumTree {
+            k,
+            stack: Vec::new(),
+            nodes: vec![W::zero()],
+            ids_to_node_indexes: HashMap::new(),
+            node_indexes_to_ids: HashMap::new(),
+            history: None,
+            hashes: None,
+            journal: None,
+        })
+    }
+
+    /**
+     *  @dev The max number of children for each node in the tree.
+     */
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /**
+     *  @dev The flattened node array, root first. Internal nodes hold the sum of their children.
+     */
+    pub fn nodes(&self) -> &[W] {
+        &self.nodes
+    }
+
+    /**
+     *  @dev The indexes of vacant leaves, reused (last in, first out) by the next insertion.
+     */
+    pub fn stack(&self) -> &[usize] {
+        &self.stack
+    }
+
+    /**
+     *  @dev The sum of all values in the tree, i.e. the root node.
+     */
+    pub fn total(&self) -> W {
+        self.nodes.first().copied().unwrap_or_else(W::zero)
+    }
+
+    /**
+     *  @dev The index at which leaves start: every node from there on has no children.
+     */
+    pub fn leaf_start_index(&self) -> usize {
+        self.nodes.len().saturating_sub(1).div_ceil(self.k)
+    }
+
+    /**
+     *  @dev The number of IDs with a non-zero value in the tree.
+     */
+    pub fn len(&self) -> usize {
+        self.ids_to_node_indexes.len()
+    }
+
+    /**
+     *  @dev Whether no ID has a non-zero value in the tree.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.ids_to_node_indexes.is_empty()
+    }
+
+    /**
+     *  @dev Iterate over the IDs and values of the tree in leaf order, skipping vacant leaves.
+     *  `O(n)` for the whole iteration where
+     *  `n` is the maximum number of nodes ever appended.
+     */
+    pub fn iter(&self) -> impl Iterator<Item = (&Id, W)> + '_ {
+        (self.leaf_start_index()..self.nodes.len()).filter_map(move |index| {
+            self.node_indexes_to_ids
+                .get(&index)
+                .map(|id| (id, self.nodes[index]))
+        })
+    }
+
+    /**
+     *  @dev Gets the index of the leaf holding an ID's value.
+     *  @param id The ID of the value.
+     *  @return index The node index, or `None` if the ID has no value in the tree.
+     */
+    pub fn node_index_of(&self, id: &Id) -> Option<usize> {
+        self.ids_to_node_indexes.get(id).copied()
+    }
+
+    /**
+     *  @dev Gets the ID stored at a leaf.
+     *  @param index The node index.
+     *  @return id The ID, or `None` if the node is vacant or internal.
+     */
+    pub fn id_at(&self, index: usize) -> Option<&Id> {
+        self.node_indexes_to_ids.get(&index)
+    }
+
+    /**
+     *  @dev Draw an ID as if some leaves held 0. The excluded values are summed along their paths to the root, and the walk subtracts them from the nodes it reads.
+     *  @param _excluded The indexes of the excluded leaves, each at most once.
+     *  @param _drawn_number The drawn number, reduced modulo the total of the other leaves.
+     *  @return ID The drawn ID.
+     */
+    fn draw_excluding_leaves(
+        &self,
+        excluded: &[usize],
+        drawn_number: U256,
+    ) -> Result<Id, SortitionError> {
+        if self.total().is_zero() {
+            return Err(SortitionError::EmptyTree);
+        }
+        let mut excluded_sums: HashMap<usize, W> = HashMap::new();
+        for &leaf_index in excluded {
+            let value = self.nodes[leaf_index];
+            let mut index = leaf_index;
+            loop {
+                let sum = excluded_sums.entry(index).or_insert_with(W::zero);
+                // Never above the node itself, which is a `W`.
+                *sum = sum.checked_add(value).ok_or(SortitionError::Overflow)?;
+                if index == 0 {
+                    break;
+                }
+                index = (index - 1) / self.k;
+            }
+        }
+        let eligible_value = |index: usize| {
+            let excluded_sum = excluded_sums.get(&index).copied().unwrap_or_else(W::zero);
+            let value = self.nodes.get(index).copied().unwrap_or_else(W::zero);
+            value
+                .checked_sub(excluded_sum)
+                .ok_or(SortitionError::Underflow)
+        };
+        let eligible_total = eligible_value(0)?;
+        if eligible_total.is_zero() {
+            return Err(SortitionError::NoEligibleId);
+        }
+        // The remainder is below `eligible_total`, which is a `W`.
+        let mut current_drawn_number = W::from_u256(drawn_number % eligible_total.into_u256())
+            .ok_or(SortitionError::Overflow)?;
+        let mut tree_index: usize = 0;
+        while self.k.saturating_mul(tree_index) < self.nodes.len() - 1 {
+            for i in 1..=self.k {
+                let node_index = (self.k * tree_index) + i;
+                let node_value = eligible_value(node_index)?;
+                if current_drawn_number >= node_value {
+                    current_drawn_number = current_drawn_number
+                        .checked_sub(node_value)
+                        .ok_or(SortitionError::Underflow)?;
+                } else {
+                    tree_index = node_index;
+                    break;
+                }
+            }
+        }
+        Ok(self.node_indexes_to_ids[&tree_index].clone())
+    }
+}
+
+/// A page of the leaves of a tree, as returned by [`SortitionSumTrees::query_leaves`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeavesPage<Id, W> {
+    /// The index at which leaves start.
+    pub start_index: usize,
+    /// The IDs and values of the non-vacant leaves of the page, in leaf order.
+    pub entries: Vec<(Id, W)>,
+    /// The cursor of the next page, or `None` if this is the last one.
+    pub next_cursor: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SortitionSumTrees<Key = TypeKey, Id = TypeAddress, W = u128, O = ()>
+where
+    Key: Hash + Eq,
+    Id: Hash + Eq,
+{
+    pub(crate) sortition_sum_trees: HashMap<Key, SortitionSumTree<Id, W>>,
+    /// Whether `begin` was called without a `commit` or `rollback` since.
+    pub(crate) in_transaction: bool,
+    /// Told about tree creations, leaf writes and draws.
+    pub(crate) observer: O,
+}
+
+/// Trees are equal when they hold the same trees and transaction state,
+/// whoever observes them.
+impl<Key, Id, W, O> PartialEq for SortitionSumTrees<Key, Id, W, O>
+where
+    Key: Hash + Eq,
+    Id: Hash + Eq,
+    W: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.sortition_sum_trees == other.sortition_sum_trees
+            && self.in_transaction == other.in_transaction
+    }
+}
+
+impl<Key, Id, W, O> Eq for SortitionSumTrees<Key, Id, W, O>
+where
+    Key: Hash + Eq,
+    Id: Hash + Eq,
+    W: Eq,
+{
+}
+
+impl<Key, Id, W, O> Default for SortitionSumTrees<Key, Id, W, O>
+where
+    Key: Hash + Eq,
+    Id: Hash + Eq,
+    O: Default,
+{
+    fn default() -> Self {
+        SortitionSumTrees {
+            sortition_sum_trees: HashMap::new(),
+            in_transaction: false,
+            observer: O::default(),
+        }
+    }
+}
+
+impl<Key, Id, W> SortitionSumTrees<Key, Id, W>
+where
+    Key: Hash + Eq,
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    pub fn new() -> SortitionSumTrees<Key, Id, W> {
+        SortitionSumTrees {
+            sortition_sum_trees: HashMap::new(),
+            in_transaction: false,
+            observer: (),
+        }
+    }
+}
+
+impl<Key, Id, W, O> SortitionSumTrees<Key, Id, W, O>
+where
+    Key: Hash + Eq,
+    Id: Hash + Eq + Clone,
+    W: Weight,
+    O: SortitionObserver<Key, Id, W>,
+{
+    /**
+     *  @dev Replace the observer of the trees, keeping the trees.
+     *  @param _observer The new observer, told about every later tree creation, leaf write and draw.
+     *  @return trees The same trees, observed by `_observer`.
+     */
+    pub fn with_observer<P>(self, observer: P) -> SortitionSumTrees<Key, Id, W, P>
+    where
+        P: SortitionObserver<Key, Id, W>,
+    {
+        SortitionSumTrees {
+            sortition_sum_trees: self.sortition_sum_trees,
+            in_transaction: self.in_transaction,
+            observer,
+        }
+    }
+
+    /**
+     *  @dev Gets the observer of the trees.
+     */
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    /**
+     *  @dev Gets a tree by its key.
+     *  @param key The key of the tree.
+     *  @return tree The tree.
+     */
+    pub fn tree(&self, key: &Key) -> Result<&SortitionSumTree<Id, W>, SortitionError> {
+        self.sortition_sum_trees
+            .get(key)
+            .ok_or(SortitionError::TreeNotFound)
+    }
+
+    pub(crate) fn tree_mut(
+        &mut self,
+        key: &Key,
+    ) -> Result<&mut SortitionSumTree<Id, W>, SortitionError> {
+        self.sortition_sum_trees
+            .get_mut(key)
+            .ok_or(SortitionError::TreeNotFound)
+    }
+
+    /**
+     *  @dev Gets a tree by its key, along with the observer to tell about its writes.
+     */
+    fn observed_tree_mut(
+        &mut self,
+        key: &Key,
+    ) -> Result<(&mut SortitionSumTree<Id, W>, &O), SortitionError> {
+        let tree = self
+            .sortition_sum_trees
+            .get_mut(key)
+            .ok_or(SortitionError::TreeNotFound)?;
+        Ok((tree, &self.observer))
+    }
+
+    /**
+     *  @dev Iterate over the IDs and values of a tree in leaf order, skipping vacant leaves.
+     *  @param _key The key of the tree.
+     *  `O(n)` for the whole iteration where
+     *  `n` is the maximum number of nodes ever appended.
+     */
+    pub fn iter(&self, key: &Key) -> Result<impl Iterator<Item = (&Id, W)> + '_, SortitionError> {
+        Ok(self.tree(key)?.iter())
+    }
+
+    /**
+     *  @dev Iterate over the IDs of a tree in leaf order.
+     *  @param _key The key of the tree.
+     */
+    pub fn ids(&self, key: &Key) -> Result<impl Iterator<Item = &Id> + '_, SortitionError> {
+        Ok(self.tree(key)?.iter().map(|(id, _)| id))
+    }
+
+    /**
+     *  @dev Gets the number of IDs with a non-zero value in a tree.
+     *  @param _key The key of the tree.
+     */
+    pub fn len(&self, key: &Key) -> Result<usize, SortitionError> {
+        Ok(self.tree(key)?.len())
+    }
+
+    /**
+     *  @dev Gets whether no ID has a non-zero value in a tree.
+     *  @param _key The key of the tree.
+     */
+    pub fn is_empty(&self, key: &Key) -> Result<bool, SortitionError> {
+        Ok(self.tree(key)?.is_empty())
+    }
+
+    /**
+     *  @dev Freeze the current values of a tree, to draw against them later. See `SortitionSumTree::checkpoint`.
+     *  @param _key The key of the tree.
+     *  @return snapshot The ID of the new checkpoint.
+     */
+    pub fn checkpoint(&mut self, key: &Key) -> Result<SnapshotId, SortitionError> {
+        Ok(self.tree_mut(key)?.checkpoint())
+    }
+
+    /**
+     *  @dev Drop a checkpoint of a tree. See `SortitionSumTree::release_checkpoint`.
+     *  @param _key The key of the tree.
+     *  @param _snapshot The checkpoint, as returned by `checkpoint`.
+     */
+    pub fn release_checkpoint(
+        &mut self,
+        key: &Key,
+        snapshot: SnapshotId,
+    ) -> Result<(), SortitionError> {
+        self.tree_mut(key)?.release_checkpoint(snapshot)
+    }
+
+    /**
+     *  @dev Gets the value an ID had in a tree at a checkpoint.
+     *  @param _key The key of the tree.
+     *  @param _snapshot The checkpoint, as returned by `checkpoint`.
+     *  @param _id The ID of the value.
+     *  @return value The associated value.
+     */
+    pub fn stake_of_at(
+        &self,
+        key: &Key,
+        snapshot: SnapshotId,
+        id: &Id,
+    ) -> Result<W, SortitionError> {
+        self.tree(key)?.stake_of_at(snapshot, id)
+    }
+
+    /**
+     *  @dev Draw an ID from a tree as it was at a checkpoint, exactly as `draw` would have then. Note that this function fails with `EmptyTree` if the sum of all values in the tree was 0.
+     *  @param _key The key of the tree.
+     *  @param _snapshot The checkpoint, as returned by `checkpoint`.
+     *  @param _drawn_number The drawn number.
+     *  @return ID The drawn ID.
+     */
+    pub fn draw_at(
+        &self,
+        key: &Key,
+        snapshot: SnapshotId,
+        drawn_number: W,
+    ) -> Result<Id, SortitionError> {
+        let id = self.tree(key)?.draw_at(snapshot, drawn_number)?;
+        self.observer.on_draw(key, &id);
+        Ok(id)
+    }
+
+    /**
+     *  @dev Check every invariant of a tree. See `SortitionSumTree::validate`.
+     *  @param _key The key of the tree.
+     *  @return violations Every broken invariant, or nothing if the tree is valid.
+     */
+    pub fn validate(&self, key: &Key) -> Result<Vec<Violation<Id, W>>, SortitionError> {
+        Ok(self.tree(key)?.validate())
+    }
+
+    /**
+     *  @dev Create a sortition sum tree with a key.
+     *  @param _key The key of the new tree.
+     *  @param _k The max number of children for each node in the new tree.
+     */
+    pub fn create_tree(&mut self, key: Key, k: usize) -> Result<(), SortitionError> {
+        let mut tree = SortitionSumTree::new(k)?;
+        if self.sortition_sum_trees.contains_key(&key) {
+            return Err(SortitionError::TreeAlreadyExists);
+        }
+        if self.in_transaction {
+            tree.journal = Some(Journal::new(true));
+        }
+        self.observer.on_tree_created(&key, k);
+        self.sortition_sum_trees.insert(key, tree);
+        Ok(())
+    }
+
+    /**
+     *  @dev Set a value of an address in a tree.
+     *  @param _key The key of the tree.
+     *  @param _value The new value.
+     *  @param _id The ID of the value.
+     *  Fails with `Overflow` (or `Underflow` on an inconsistent tree), leaving the tree untouched, if any node sum on the path to the root would leave the range of `W`.
+     *  `O(log_k(n))` where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     *  Once the tree has been checkpointed, every node and ID write is done twice: in the tree, and in the persistent trie of its history, where it copies up to `log_32(n)` trie nodes still shared with a checkpoint. This lasts even after every checkpoint is released.
+     */
+    pub fn set(&mut self, key: &Key, value: W, id: Id) -> Result<(), SortitionError> {
+        let (tree, observer) = self.observed_tree_mut(key)?;
+        set_observed(tree, value, id, key, observer)
+    }
+
+    /**
+     *  @dev Set the values of several addresses in a tree, as if by calling `set` for each of them in order, but recomputing each internal node at most once.
+     *  @param _key The key of the tree.
+     *  @param _entries The `(ID, new value)` pairs. The last value of an ID that appears several times wins.
+     *  Fails with `Overflow` (or `Underflow` on an inconsistent tree), leaving the tree untouched, if the new sum of all values would leave the range of `W`.
+     *  `O(m * k * log_k(n))` where
+     *  `m` is the number of entries,
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    pub fn set_many(
+        &mut self,
+        key: &Key,
+        entries: impl IntoIterator<Item = (Id, W)>,
+    ) -> Result<(), SortitionError> {
+        let (tree, observer) = self.observed_tree_mut(key)?;
+        set_many_observed(tree, entries, key, observer)
+    }
+
+    /**
+     *  @dev Add to the value of an address in a tree, inserting it if it has none.
+     *  @param _key The key of the tree.
+     *  @param _amount The value to add.
+     *  @param _id The ID of the value.
+     *  Fails with `Overflow`, leaving the tree untouched, if the value or a node sum on the path to the root would leave the range of `W`.
+     *  `O(log_k(n))` where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    pub fn increase_stake(&mut self, key: &Key, amount: W, id: Id) -> Result<(), SortitionError> {
+        let (tree, observer) = self.observed_tree_mut(key)?;
+        let value = NodeStore::stake_of(tree, &id)
+            .checked_add(amount)
+            .ok_or(SortitionError::Overflow)?;
+        set_observed(tree, value, id, key, observer)
+    }
+
+    /**
+     *  @dev Subtract from the value of an address in a tree, removing it if its value drops to 0.
+     *  @param _key The key of the tree.
+     *  @param _amount The value to subtract.
+     *  @param _id The ID of the value.
+     *  Fails with `InsufficientStake`, leaving the tree untouched, if the value of the address is lower than `_amount`.
+     *  `O(log_k(n))` where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    pub fn decrease_stake(&mut self, key: &Key, amount: W, id: Id) -> Result<(), SortitionError> {
+        let (tree, observer) = self.observed_tree_mut(key)?;
+        let value = NodeStore::stake_of(tree, &id)
+            .checked_sub(amount)
+            .ok_or(SortitionError::InsufficientStake)?;
+        set_observed(tree, value, id, key, observer)
+    }
+
+    /**
+     *  @dev Move part of the value of an address to another address in a tree, as a single operation: either both values change or neither does.
+     *  @param _key The key of the tree.
+     *  @param _from The ID to take the value from. It is removed if its value drops to 0.
+     *  @param _to The ID to give the value to. It is inserted if it has no value.
+     *  @param _amount The value to move.
+     *  Fails with `InsufficientStake` if the value of `_from` is lower than `_amount`, and with `Overflow` if the value of `_to` would leave the range of `W`, leaving the tree untouched. Both values are written by one `set_many`, which checks every sum before writing.
+     *  `O(k * log_k(n))` where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    pub fn transfer_stake(
+        &mut self,
+        key: &Key,
+        from: Id,
+        to: Id,
+        amount: W,
+    ) -> Result<(), SortitionError> {
+        let (tree, observer) = self.observed_tree_mut(key)?;
+        let from_value = NodeStore::stake_of(tree, &from);
+        let new_from_value = from_value
+            .checked_sub(amount)
+            .ok_or(SortitionError::InsufficientStake)?;
+        if from == to {
+            return Ok(());
+        }
+        let new_to_value = NodeStore::stake_of(tree, &to)
+            .checked_add(amount)
+            .ok_or(SortitionError::Overflow)?;
+        set_many_observed(
+            tree,
+            [(from, new_from_value), (to, new_to_value)],
+            key,
+            observer,
+        )
+    }
+
+    /** @dev Gets a specified ID's associated value.
+     *  @param _key The key of the tree.
+     *  @param _id The ID of the value.
+     *  @return value The associated value.
+     */
+    pub fn stake_of(&self, key: &Key, id: &Id) -> Result<W, SortitionError> {
+        Ok(NodeStore::stake_of(self.tree(key)?, id))
+    }
+
+    /**
+     *  @dev Draw an ID from a tree using a number. Note that this function fails with `EmptyTree` if the sum of all values in the tree is 0.
+     *  @param _key The key of the tree.
+     *  @param _drawn_number The drawn number.
+     *  @return ID The drawn ID.
+     *  `O(k * log_k(n))` where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    pub fn draw(&self, key: &Key, drawn_number: W) -> Result<Id, SortitionError> {
+        let id = NodeStore::draw(self.tree(key)?, drawn_number)?;
+        self.observer.on_draw(key, &id);
+        Ok(id)
+    }
+
+    /**
+     *  @dev Draw an ID from a tree using a 256-bit number, reduced modulo the sum of all values in 256 bits like the Solidity `uint` draw.
+     *  Use this to replay on-chain draws whose number does not fit in `W`.
+     *  @param _key The key of the tree.
+     *  @param _drawn_number The drawn number.
+     *  @return ID The drawn ID.
+     *  `O(k * log_k(n))`
+     */
+    pub fn draw_u256(&self, key: &Key, drawn_number: U256) -> Result<Id, SortitionError> {
+        let total = self.tree(key)?.total();
+        if total.is_zero() {
+            return Err(SortitionError::EmptyTree);
+        }
+        let reduced = drawn_number % total.into_u256();
+        // The remainder is below `total`, which is a `W`.
+        self.draw(key, W::from_u256(reduced).ok_or(SortitionError::Overflow)?)
+    }
+
+    /**
+     *  @dev Draw an ID from a tree using a seed, a dispute ID and a nonce, deriving the number as `uint256(keccak256(abi.encodePacked(RN, disputeID, nonce)))` like Kleros court contracts.
+     *  Given the same tree, the result matches the on-chain draw bit for bit.
+     *  @param _key The key of the tree.
+     *  @param _seed The random seed, i.e. the random number `RN` as a `uint256`.
+     *  @param _dispute_id The ID of the dispute the draw is for.
+     *  @param _nonce The index of the draw, encoded as a `uint256`.
+     *  @return ID The drawn ID.
+     *  `O(k * log_k(n))`
+     */
+    pub fn draw_from_seed(
+        &self,
+        key: &Key,
+        seed: [u8; 32],
+        dispute_id: U256,
+        nonce: u64,
+    ) -> Result<Id, SortitionError> {
+        self.draw_u256(key, drawn_number(&seed, dispute_id, nonce))
+    }
+
+    /**
+     *  @dev Draw several IDs from a tree with replacement, using nonces `0` to `_n - 1` like consecutive `draw_from_seed` calls.
+     *  @param _key The key of the tree.
+     *  @param _seed The random seed.
+     *  @param _dispute_id The ID of the dispute the draws are for.
+     *  @param _n The number of IDs to draw.
+     *  @return IDs The drawn IDs, in nonce order. An ID can appear several times.
+     *  `O(n * k * log_k(n))`
+     */
+    pub fn draw_many(
+        &self,
+        key: &Key,
+        seed: [u8; 32],
+        dispute_id: U256,
+        n: usize,
+    ) -> Result<Vec<Id>, SortitionError> {
+        (0..n as u64)
+            .map(|nonce| self.draw_from_seed(key, seed, dispute_id, nonce))
+            .collect()
+    }
+
+    /**
+     *  @dev Draw several distinct IDs from a tree without replacement. Each draw takes the leaves of the IDs drawn before it out of the drawn range, so the tree is never written to.
+     *  @param _key The key of the tree.
+     *  @param _seed The random seed.
+     *  @param _dispute_id The ID of the dispute the draws are for.
+     *  @param _n The number of IDs to draw. Fewer are returned if the tree runs out of IDs.
+     *  @return IDs The drawn IDs, in nonce order. The `i`th ID is drawn with nonce `i` from the tree without the IDs before it.
+     *  `O(_n * (_n + k) * log_k(n))`
+     */
+    pub fn draw_distinct(
+        &self,
+        key: &Key,
+        seed: [u8; 32],
+        dispute_id: U256,
+        n: usize,
+    ) -> Result<Vec<Id>, SortitionError> {
+        let tree = self.tree(key)?;
+        let mut ids = Vec::new();
+        let mut leaves = Vec::new();
+        for nonce in 0..n as u64 {
+            let id =
+                match tree.draw_excluding_leaves(&leaves, drawn_number(&seed, dispute_id, nonce)) {
+                    Ok(id) => id,
+                    Err(SortitionError::NoEligibleId) => break,
+                    Err(error) => return Err(error),
+                };
+            self.observer.on_draw(key, &id);
+            leaves.push(tree.ids_to_node_indexes[&id]);
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+
+    /**
+     *  @dev Draw an ID from a tree using random bytes, without the modulo bias of `draw`.
+     *  A point is drawn uniformly from `[0, total)` by rejection sampling, so every ID is drawn with probability exactly `value / total`.
+     *  @param _key The key of the tree.
+     *  @param _source The source of random bytes.
+     *  @return ID The drawn ID.
+     *  `O(k * log_k(n))`, plus an expected number of draws from `_source` below 2.
+     */
+    pub fn draw_unbiased<R>(&self, key: &Key, source: &mut R) -> Result<Id, SortitionError>
+    where
+        R: RandomSource + ?Sized,
+    {
+        let total = self.tree(key)?.total();
+        if total.is_zero() {
+            return Err(SortitionError::EmptyTree);
+        }
+        self.draw(key, uniform_below(source, total))
+    }
+
+    /**
+     *  @dev Draw an ID from a tree using a seed, among the IDs that are not excluded. Excluded values are taken out of the drawn range, so every other ID is drawn with probability exactly `value / eligible total` (up to the modulo reduction of `draw_from_seed`).
+     *  The number is derived like `draw_from_seed` with nonce 0, then reduced modulo the eligible total; use a different seed for each draw.
+     *  @param _key The key of the tree.
+     *  @param _seed The random seed.
+     *  @param _dispute_id The ID of the dispute the draw is for.
+     *  @param _excluded The IDs that cannot be drawn. IDs without a value in the tree are ignored.
+     *  @return ID The drawn ID. Fails with `NoEligibleId` if every ID with a value is excluded.
+     *  `O((|excluded| + k) * log_k(n))`
+     */
+    pub fn draw_excluding<S>(
+        &self,
+        key: &Key,
+        seed: [u8; 32],
+        dispute_id: U256,
+        excluded: &HashSet<Id, S>,
+    ) -> Result<Id, SortitionError>
+    where
+        S: BuildHasher,
+    {
+        let tree = self.tree(key)?;
+        let leaves: Vec<usize> = excluded
+            .iter()
+            .filter_map(|id| tree.node_index_of(id))
+            .collect();
+        let id = tree.draw_excluding_leaves(&leaves, drawn_number(&seed, dispute_id, 0))?;
+        self.observer.on_draw(key, &id);
+        Ok(id)
+    }
+
+    /**
+     *  @dev Draw an ID from a tree using a seed, among the IDs for which a predicate holds. See `draw_excluding`.
+     *  @param _key The key of the tree.
+     *  @param _seed The random seed.
+     *  @param _dispute_id The ID of the dispute the draw is for.
+     *  @param _is_eligible Whether an ID can be drawn. Called once for every ID with a value in the tree.
+     *  @return ID The drawn ID. Fails with `NoEligibleId` if no ID with a value is eligible.
+     *  `O(n)` where
+     *  `n` is the maximum number of nodes ever appended.
+     */
+    pub fn draw_filtered<F>(
+        &self,
+        key: &Key,
+        seed: [u8; 32],
+        dispute_id: U256,
+        mut is_eligible: F,
+    ) -> Result<Id, SortitionError>
+    where
+        F: FnMut(&Id) -> bool,
+    {
+        let tree = self.tree(key)?;
+        let leaves: Vec<usize> = (tree.leaf_start_index()..tree.nodes.len())
+            .filter(|index| {
+                tree.node_indexes_to_ids
+                    .get(index)
+                    .is_some_and(|id| !is_eligible(id))
+            })
+            .collect();
+        let id = tree.draw_excluding_leaves(&leaves, drawn_number(&seed, dispute_id, 0))?;
+        self.observer.on_draw(key, &id);
+        Ok(id)
+    }
+
+    /**
+     *  @dev Query the leaves of a tree, skipping vacant ones. Note that if `start_index == 0`, the tree is empty and no entries are returned.
+     *  @param key The key of the tree to get the leaves from.
+     *  @param cursor The pagination cursor, i.e. the number of leaf slots to skip from `start_index`.
+     *  @param count The max number of entries to return.
+     *  @return page The leaves, and the cursor of the next page if there are more.
+     *  `O(n)` where
+     *  `n` is the maximum number of nodes ever appended.
+     */
+    pub fn query_leaves(
+        &self,
+        key: &Key,
+        cursor: usize,
+        count: usize,
+    ) -> Result<LeavesPage<Id, W>, SortitionError> {
+        Ok(NodeStore::query_leaves(self.tree(key)?, cursor, count))
+    }
+}
@@ -0,0 +1,702 @@
+//! The storage of a single tree, abstracted so that trees can live outside
+//! of memory.
+//!
+//! [`NodeStore`] provides `set`, `set_many`, `stake_of`, `draw` and
+//! `query_leaves` on top of a handful of accessors. They only touch the nodes
+//! on the path of the leaves they work on, so a store backed by contract storage, such as
+//! `LookupMapStore` with the `near` feature, reads and writes
+//! `O(k * log_k(n))` nodes per call instead of loading the whole tree.
+
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+
+use crate::error::SortitionError;
+use crate::journal::Change;
+use crate::observer::SortitionObserver;
+use crate::sortition_sum_tree::{LeavesPage, SortitionSumTree};
+use crate::weight::Weight;
+
+pub(crate) mod raw {
+    /// The unchecked writes [`NodeStore`](super::NodeStore) builds its
+    /// operations on. The module is private, so outside of the crate a tree
+    /// only changes through the checked operations.
+    pub trait RawNodeStore<Id, W> {
+        /// Overwrites the value of an existing node.
+        fn set_node(&mut self, index: usize, value: W);
+
+        /// Appends a node at index `node_count`.
+        fn push_node(&mut self, value: W);
+
+        /// Removes the vacant leaf returned by `last_vacant`.
+        fn pop_vacant(&mut self) -> Option<usize>;
+
+        /// Marks a leaf as vacant.
+        fn push_vacant(&mut self, index: usize);
+
+        /// Stores an ID at a leaf, in both directions.
+        fn link(&mut self, index: usize, id: Id);
+
+        /// Removes the ID stored at a leaf, in both directions, and returns it.
+        fn unlink(&mut self, index: usize) -> Option<Id>;
+    }
+}
+
+use raw::RawNodeStore;
+
+/// Where a tree keeps its nodes, its stack of vacant leaves and the IDs of
+/// its leaves.
+///
+/// A store always holds at least the root node, at index 0. The trait is
+/// sealed: it is implemented by [`SortitionSumTree`] and, with the `near`
+/// feature, `LookupMapStore`.
+pub trait NodeStore<Id, W: Weight>: RawNodeStore<Id, W> {
+    /// The max number of children for each node in the tree.
+    fn k(&self) -> usize;
+
+    /// The number of nodes ever appended, including the root.
+    fn node_count(&self) -> usize;
+
+    /// The value of a node. Nodes past `node_count` read as 0.
+    fn node(&self, index: usize) -> W;
+
+    /// The vacant leaf that the next insertion will reuse, if any.
+    fn last_vacant(&self) -> Option<usize>;
+
+    /// The index of the leaf holding an ID's value.
+    fn index_of(&self, id: &Id) -> Option<usize>;
+
+    /// The ID stored at a leaf.
+    fn id_of(&self, index: usize) -> Option<Id>;
+
+    /**
+     *  @dev The sum of all values in the tree, i.e. the root node.
+     */
+    fn total(&self) -> W {
+        self.node(0)
+    }
+
+    /**
+     *  @dev Set a value of an address in the tree.
+     *  @param _value The new value.
+     *  @param _id The ID of the value.
+     *  Fails with `Overflow` (or `Underflow` on an inconsistent tree), leaving the tree untouched, if any node sum on the path to the root would leave the range of `W`.
+     *  `O(log_k(n))` where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    fn set(&mut self, value: W, id: Id) -> Result<(), SortitionError> {
+        set_observed(self, value, id, &(), &())
+    }
+
+    /**
+     *  @dev Set the values of several addresses in the tree, as if by calling `set` for each of them in order, but recomputing each internal node at most once.
+     *  @param _entries The `(ID, new value)` pairs. The last value of an ID that appears several times wins.
+     *  Fails with `Overflow` (or `Underflow` on an inconsistent tree), leaving the tree untouched, if the new sum of all values would leave the range of `W`.
+     *  `O(m * k * log_k(n))` where
+     *  `m` is the number of entries,
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended. The nodes near the root are shared by all entries, so large batches write far fewer nodes than `m` calls to `set`.
+     */
+    fn set_many<I>(&mut self, entries: I) -> Result<(), SortitionError>
+    where
+        I: IntoIterator<Item = (Id, W)>,
+        Id: Hash + Eq,
+        Self: Sized,
+    {
+        set_many_observed(self, entries, &(), &())
+    }
+
+    /** @dev Gets a specified ID's associated value.
+     *  @param _id The ID of the value.
+     *  @return value The associated value.
+     */
+    fn stake_of(&self, id: &Id) -> W {
+        match self.index_of(id) {
+            Some(tree_index) => self.node(tree_index),
+            None => W::zero(),
+        }
+    }
+
+    /**
+     *  @dev Draw an ID from the tree using a number. Note that this function fails with `EmptyTree` if the sum of all values in the tree is 0.
+     *  @param _drawn_number The drawn number.
+     *  @return ID The drawn ID.
+     *  `O(k * log_k(n))` where
+     *  `k` is the maximum number of childs per node in the tree,
+     *   and `n` is the maximum number of nodes ever appended.
+     */
+    fn draw(&self, drawn_number: W) -> Result<Id, SortitionError> {
+        let tree_index = draw_leaf(
+            self.k(),
+            self.node_count(),
+            |index| self.node(index),
+            drawn_number,
+        )?;
+        Ok(self
+            .id_of(tree_index)
+            .expect("a drawn leaf with a non-zero value is labelled"))
+    }
+
+    /**
+     *  @dev Query the leaves of the tree, skipping vacant ones. Note that if `start_index == 0`, the tree is empty and no entries are returned.
+     *  @param cursor The pagination cursor, i.e. the number of leaf slots to skip from `start_index`.
+     *  @param count The max number of entries to return.
+     *  @return page The leaves, and the cursor of the next page if there are more.
+     *  `O(count)` node reads, plus one per vacant leaf skipped.
+     */
+    fn query_leaves(&self, cursor: usize, count: usize) -> LeavesPage<Id, W> {
+        let node_count = self.node_count();
+        let start_index = node_count.saturating_sub(1).div_ceil(self.k());
+        let mut entries: Vec<(Id, W)> = Vec::new();
+        let mut next_cursor: Option<usize> = None;
+        let loop_start_index = start_index.saturating_add(cursor);
+        for j in loop_start_index..node_count {
+            if let Some(id) = self.id_of(j) {
+                if entries.len() < count {
+                    entries.push((id, self.node(j)));
+                } else {
+                    next_cursor = Some(j - start_index);
+                    break;
+                }
+            }
+        }
+        LeavesPage {
+            start_index,
+            entries,
+            next_cursor,
+        }
+    }
+}
+
+/**
+ *  @dev `NodeStore::set`, reporting the changes of leaves to an observer.
+ *  @param _key The key of the tree, passed to the observer.
+ *  @param _observer The observer of the tree.
+ */
+pub(crate) fn set_observed<S, Key, Id, W, O>(
+    store: &mut S,
+    value: W,
+    id: Id,
+    key: &Key,
+    observer: &O,
+) -> Result<(), SortitionError>
+where
+    S: NodeStore<Id, W> + ?Sized,
+    W: Weight,
+    O: SortitionObserver<Key, Id, W> + ?Sized,
+{
+    let slot = store
+        .index_of(&id)
+        .map(|tree_index| (tree_index, store.node(tree_index)));
+    let parents = if let Some((tree_index, current_value)) = slot {
+        //node exist
+        if value.is_zero() {
+            //new value==0
+            //remove
+            checked_parents(store, tree_index, false, current_value)?
+        } else if value != current_value {
+            // New value,and!=0
+            // Set.
+            let plus_or_minus = current_value <= value;
+            let plus_or_minus_value: W = if plus_or_minus {
+                value.checked_sub(current_value)
+            } else {
+                current_value.checked_sub(value)
+            }
+            .ok_or(SortitionError::Underflow)?;
+            checked_parents(store, tree_index, plus_or_minus, plus_or_minus_value)?
+        } else {
+            return Ok(());
+        }
+    } else if !value.is_zero() {
+        //node not exist
+        // The parents of the new leaf already exist, even when it is appended
+        // as a first child, so the whole path is validated before any write.
+        let tree_index = match store.last_vacant() {
+            Some(vacant_index) => vacant_index,
+            None => store.node_count(),
+        };
+        checked_parents(store, tree_index, true, value)?
+    } else {
+        return Ok(());
+    };
+    write_leaf(store, slot, value, id, key, observer);
+    update_parents(store, parents);
+    Ok(())
+}
+
+/**
+ *  @dev `NodeStore::set_many`, reporting the changes of leaves to an observer.
+ *  @param _key The key of the tree, passed to the observer.
+ *  @param _observer The observer of the tree.
+ */
+pub(crate) fn set_many_observed<S, I, Key, Id, W, O>(
+    store: &mut S,
+    entries: I,
+    key: &Key,
+    observer: &O,
+) -> Result<(), SortitionError>
+where
+    S: NodeStore<Id, W> + ?Sized,
+    I: IntoIterator<Item = (Id, W)>,
+    Id: Hash + Eq,
+    W: Weight,
+    O: SortitionObserver<Key, Id, W> + ?Sized,
+{
+    let entries: Vec<(Id, W)> = entries.into_iter().collect();
+    {
+        let mut last_values: HashMap<&Id, W> = HashMap::new();
+        for (id, value) in &entries {
+            last_values.insert(id, *value);
+        }
+        // Every node is at most the root, so a new total in range means no
+        // sum overflows. The old values are all taken out first, so that a
+        // decrease later in the batch makes room for an increase before it.
+        let mut total = store.total();
+        for &id in last_values.keys() {
+            total = total
+                .checked_sub(store.stake_of(id))
+                .ok_or(SortitionError::Underflow)?;
+        }
+        for &value in last_values.values() {
+            total = total.checked_add(value).ok_or(SortitionError::Overflow)?;
+        }
+    }
+
+    let k = store.k();
+    let mut written = Vec::new();
+    for (id, value) in entries {
+        let slot = store
+            .index_of(&id)
+            .map(|tree_index| (tree_index, store.node(tree_index)));
+        written.extend(write_leaf(store, slot, value, id, key, observer));
+    }
+    let node_count = store.node_count();
+    for index in affected_ancestors(k, written) {
+        let first_child = k.saturating_mul(index).saturating_add(1);
+        if first_child >= node_count {
+            continue;
+        }
+        let last_child = first_child.saturating_add(k).min(node_count);
+        let sum = (first_child..last_child).fold(W::zero(), |sum, child| {
+            sum.checked_add(store.node(child))
+                .expect("a node sum is at most the validated total")
+        });
+        store.set_node(index, sum);
+    }
+    Ok(())
+}
+
+/**
+ *  @dev Collect some nodes and all their ancestors, in an order where every node comes after its children.
+ *  @param _k The max number of children for each node in the tree.
+ *  @param _indexes The nodes.
+ *  @return affected The nodes and their ancestors, from the highest index down to the root.
+ *  `O(m * log_k(n))` where
+ *  `m` is the number of nodes given,
+ *  `k` is the maximum number of childs per node in the tree,
+ *   and `n` is the maximum number of nodes ever appended.
+ */
+pub(crate) fn affected_ancestors(
+    k: usize,
+    indexes: impl IntoIterator<Item = usize>,
+) -> impl Iterator<Item = usize> {
+    let mut affected = BTreeSet::new();
+    for mut index in indexes {
+        // Stop at the first node already affected: its parents are too.
+        while affected.insert(index) && index != 0 {
+            index = (index - 1) / k;
+        }
+    }
+    // Children come after their parent, so walking the indexes backwards
+    // visits every node after its children.
+    affected.into_iter().rev()
+}
+
+/**
+ *  @dev Walk down a tree with a number, whatever holds its nodes. Note that this function fails with `EmptyTree` if the sum of all values in the tree is 0.
+ *  @param _k The max number of children for each node in the tree.
+ *  @param _node_count The number of nodes ever appended, including the root.
+ *  @param _node The value of a node, 0 past `_node_count`.
+ *  @param _drawn_number The drawn number.
+ *  @return tree_index The index of the drawn leaf.
+ */
+pub(crate) fn draw_leaf<W: Weight>(
+    k: usize,
+    node_count: usize,
+    node: impl Fn(usize) -> W,
+    drawn_number: W,
+) -> Result<usize, SortitionError> {
+    let total = node(0);
+    if total.is_zero() {
+        return Err(SortitionError::EmptyTree);
+    }
+    let mut tree_index: usize = 0;
+    let mut current_drawn_number = drawn_number.modulo(total);
+    // Saturating, as a K loaded from a snapshot may be close to `usize::MAX`.
+    while k.saturating_mul(tree_index) < node_count - 1 {
+        for i in 1..=k {
+            let node_index = (k * tree_index) + i;
+            let node_value = node(node_index);
+            if current_drawn_number >= node_value {
+                current_drawn_number = current_drawn_number
+                    .checked_sub(node_value)
+                    .ok_or(SortitionError::Underflow)?;
+            } else {
+                tree_index = node_index;
+                break;
+            }
+        }
+    }
+    Ok(tree_index)
+}
+
+/**
+ *  @dev Write a new value to the leaf of an ID, without updating its parents.
+ *  Inserting may append a node, moving its parent leaf down; removing vacates the leaf.
+ *  @param _slot The index and value of the leaf of the ID, if it has one.
+ *  @param _value The new value.
+ *  @param _id The ID of the value.
+ *  @param _key The key of the tree, passed to the observer.
+ *  @param _observer Told about every leaf written.
+ *  @return tree_index The index of the leaf written, if any.
+ */
+fn write_leaf<S, Key, Id, W, O>(
+    store: &mut S,
+    slot: Option<(usize, W)>,
+    value: W,
+    id: Id,
+    key: &Key,
+    observer: &O,
+) -> Option<usize>
+where
+    S: NodeStore<Id, W> + ?Sized,
+    W: Weight,
+    O: SortitionObserver<Key, Id, W> + ?Sized,
+{
+    if let Some((tree_index, current_value)) = slot {
+        //node exist
+        if value.is_zero() {
+            //new value==0
+            //remove
+            store.set_node(tree_index, W::zero());
+            store.push_vacant(tree_index);
+            store.unlink(tree_index);
+            observer.on_stake_removed(key, &id, current_value, tree_index);
+        } else {
+            // New value,and!=0
+            // Set.
+            store.set_node(tree_index, value);
+            if value != current_value {
+                observer.on_stake_updated(key, &id, current_value, value, tree_index);
+            }
+        }
+        Some(tree_index)
+    } else if !value.is_zero() {
+        //node not exist
+        let tree_index = match store.pop_vacant() {
+            Some(vacant_index) => {
+                //vacant node
+                store.set_node(vacant_index, value);
+                vacant_index
+            }
+            None => {
+                //no vacant node
+                let tree_index = store.node_count();
+                store.push_node(value);
+                let k = store.k();
+                if (tree_index != 1) && (tree_index - 1).is_multiple_of(k) {
+                    //is the first child node.
+                    //move the parent  down
+                    let parent_index = tree_index / k;
+                    let new_index = tree_index + 1;
+                    store.push_node(store.node(parent_index));
+                    let parent_id = store
+                        .unlink(parent_index)
+                        .expect("the parent of a first child is a labelled leaf");
+                    observer.on_leaf_relocated(key, &parent_id, parent_index, new_index);
+                    store.link(new_index, parent_id);
+                }
+                tree_index
+            }
+        };
+        observer.on_stake_inserted(key, &id, value, tree_index);
+        store.link(tree_index, id);
+        Some(tree_index)
+    } else {
+        None
+    }
+}
+
+/**
+ *  @dev Compute the new values of the parents of a node until root, without writing them.
+ *  Every sum on the path is checked, so a failed update leaves the tree untouched.
+ *  @param _tree_index The index of the node to start from.
+ *  @param _plus_or_minus Wether to add (true) or substract (false).
+ *  @param _value The value to add or substract.
+ *  @return parents The `(index, new value)` pairs from the direct parent up to the root.
+ */
+fn checked_parents<S, Id, W>(
+    store: &S,
+    tree_index: usize,
+    plus_or_minus: bool,
+    value: W,
+) -> Result<Vec<(usize, W)>, SortitionError>
+where
+    S: NodeStore<Id, W> + ?Sized,
+    W: Weight,
+{
+    let k = store.k();
+    let mut parents = Vec::new();
+    let mut parent_index = tree_index;
+    while parent_index != 0 {
+        parent_index = (parent_index - 1) / k;
+        let parent_value = if plus_or_minus {
+            store
+                .node(parent_index)
+                .checked_add(value)
+                .ok_or(SortitionError::Overflow)?
+        } else {
+            store
+                .node(parent_index)
+                .checked_sub(value)
+                .ok_or(SortitionError::Underflow)?
+        };
+        parents.push((parent_index, parent_value));
+    }
+    Ok(parents)
+}
+
+/**
+ *  @dev Update the parents of a node until root.
+ *  @param _parents The new parent values, as computed by `checked_parents`.
+ */
+fn update_parents<S, Id, W>(store: &mut S, parents: Vec<(usize, W)>)
+where
+    S: NodeStore<Id, W> + ?Sized,
+    W: Weight,
+{
+    for (parent_index, parent_value) in parents {
+        store.set_node(parent_index, parent_value);
+    }
+}
+
+/// The in-memory store: every node and ID of the tree is held in the struct.
+impl<Id, W> NodeStore<Id, W> for SortitionSumTree<Id, W>
+where
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    fn k(&self) -> usize {
+        self.k
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn node(&self, index: usize) -> W {
+        self.nodes.get(index).copied().unwrap_or_else(W::zero)
+    }
+
+    fn last_vacant(&self) -> Option<usize> {
+        self.stack.last().copied()
+    }
+
+    fn index_of(&self, id: &Id) -> Option<usize> {
+        self.ids_to_node_indexes.get(id).copied()
+    }
+
+    fn id_of(&self, index: usize) -> Option<Id> {
+        self.node_indexes_to_ids.get(&index).cloned()
+    }
+}
+
+impl<Id, W> RawNodeStore<Id, W> for SortitionSumTree<Id, W>
+where
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    fn set_node(&mut self, index: usize, value: W) {
+        let previous = std::mem::replace(&mut self.nodes[index], value);
+        if let Some(journal) = &mut self.journal {
+            journal.record(Change::SetNode { index, previous });
+            journal.save_history(&self.history);
+        }
+        if let Some(history) = &mut self.history {
+            history.set_node(index, value);
+        }
+        if let Some(hashes) = &mut self.hashes {
+            hashes.touch(index);
+        }
+    }
+
+    fn push_node(&mut self, value: W) {
+        self.nodes.push(value);
+        if let Some(journal) = &mut self.journal {
+            journal.record(Change::PushNode);
+            journal.save_history(&self.history);
+        }
+        if let Some(history) = &mut self.history {
+            history.push_node(value);
+        }
+        if let Some(hashes) = &mut self.hashes {
+            hashes.touch(self.nodes.len() - 1);
+        }
+    }
+
+    fn pop_vacant(&mut self) -> Option<usize> {
+        let index = self.stack.pop()?;
+        if let Some(journal) = &mut self.journal {
+            journal.record(Change::PopVacant { index });
+        }
+        Some(index)
+    }
+
+    fn push_vacant(&mut self, index: usize) {
+        self.stack.push(index);
+        if let Some(journal) = &mut self.journal {
+            journal.record(Change::PushVacant);
+        }
+    }
+
+    fn link(&mut self, index: usize, id: Id) {
+        if let Some(journal) = &mut self.journal {
+            journal.record(Change::Link { index });
+            journal.save_history(&self.history);
+        }
+        if let Some(history) = &mut self.history {
+            let change = history.link(index, &id);
+            if let Some(journal) = &mut self.journal {
+                journal.record(Change::Index {
+                    id: id.clone(),
+                    change,
+                });
+            }
+        }
+        if let Some(hashes) = &mut self.hashes {
+            hashes.touch(index);
+        }
+        self.ids_to_node_indexes.insert(id.clone(), index);
+        self.node_indexes_to_ids.insert(index, id);
+    }
+
+    fn unlink(&mut self, index: usize) -> Option<Id> {
+        let id = self.node_indexes_to_ids.remove(&index)?;
+        self.ids_to_node_indexes.remove(&id);
+        if let Some(journal) = &mut self.journal {
+            journal.record(Change::Unlink {
+                index,
+                id: id.clone(),
+            });
+            journal.save_history(&self.history);
+        }
+        if let Some(history) = &mut self.history {
+            let change = history.unlink(index, &id);
+            if let Some(journal) = &mut self.journal {
+                journal.record(Change::Index {
+                    id: id.clone(),
+                    change,
+                });
+            }
+        }
+        if let Some(hashes) = &mut self.hashes {
+            hashes.touch(index);
+        }
+        Some(id)
+    }
+}
+
+// Counting accesses takes a store of our own, and `NodeStore` is sealed.
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::raw::RawNodeStore;
+    use super::NodeStore;
+    use crate::sortition_sum_tree::SortitionSumTree;
+
+    /// A store counting the node reads and writes of the wrapped tree.
+    struct CountingStore {
+        tree: SortitionSumTree<u64, u64>,
+        reads: Cell<usize>,
+        writes: usize,
+    }
+
+    impl NodeStore<u64, u64> for CountingStore {
+        fn k(&self) -> usize {
+            self.tree.k()
+        }
+        fn node_count(&self) -> usize {
+            self.tree.node_count()
+        }
+        fn node(&self, index: usize) -> u64 {
+            self.reads.set(self.reads.get() + 1);
+            self.tree.node(index)
+        }
+        fn last_vacant(&self) -> Option<usize> {
+            self.tree.last_vacant()
+        }
+        fn index_of(&self, id: &u64) -> Option<usize> {
+            self.tree.index_of(id)
+        }
+        fn id_of(&self, index: usize) -> Option<u64> {
+            self.tree.id_of(index)
+        }
+    }
+
+    impl RawNodeStore<u64, u64> for CountingStore {
+        fn set_node(&mut self, index: usize, value: u64) {
+            self.writes += 1;
+            self.tree.set_node(index, value)
+        }
+        fn push_node(&mut self, value: u64) {
+            self.writes += 1;
+            self.tree.push_node(value)
+        }
+        fn pop_vacant(&mut self) -> Option<usize> {
+            self.tree.pop_vacant()
+        }
+        fn push_vacant(&mut self, index: usize) {
+            self.tree.push_vacant(index)
+        }
+        fn link(&mut self, index: usize, id: u64) {
+            self.tree.link(index, id)
+        }
+        fn unlink(&mut self, index: usize) -> Option<u64> {
+            self.tree.unlink(index)
+        }
+    }
+
+    #[test]
+    fn path_only_access_test() {
+        let mut store = CountingStore {
+            tree: SortitionSumTree::new(4).unwrap(),
+            reads: Cell::new(0),
+            writes: 0,
+        };
+        for id in 0..10_000 {
+            store.set(id % 7 + 1, id).unwrap();
+        }
+        // 10_000 leaves need 7 levels below the root with K = 4.
+        let depth = 7;
+
+        store.reads.set(0);
+        store.writes = 0;
+        store.set(1_000, 4_321).unwrap();
+        assert!(store.reads.get() <= depth + 1);
+        assert!(store.writes <= depth + 1);
+
+        store.reads.set(0);
+        store.writes = 0;
+        store.set(0, 1_234).unwrap();
+        store.set(3, 10_000).unwrap();
+        assert!(store.reads.get() <= 2 * (depth + 1));
+        assert!(store.writes <= 2 * (depth + 1));
+
+        store.reads.set(0);
+        let total = store.total();
+        store.draw(total / 3).unwrap();
+        assert!(store.reads.get() <= 1 + 4 * depth);
+    }
+}
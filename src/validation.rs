@@ -0,0 +1,325 @@
+//! An invariant checker for the internals of a tree.
+
+use std::fmt;
+use std::hash::Hash;
+
+use crate::sortition_sum_tree::SortitionSumTree;
+use crate::weight::Weight;
+
+/// A broken invariant of a tree, as reported by [`SortitionSumTree::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation<Id, W> {
+    /// K is below 2. No other invariant is checked.
+    InvalidK { k: usize },
+    /// The tree has no root node. No other invariant is checked.
+    MissingRoot,
+    /// An internal node does not hold the sum of its children, or the sum
+    /// overflows (`children_sum` is `None`).
+    WrongSum {
+        index: usize,
+        value: W,
+        children_sum: Option<W>,
+    },
+    /// A stacked index is the root, an internal node or past the last node.
+    StackedNotLeaf { index: usize },
+    /// An index is on the stack more than once.
+    StackedTwice { index: usize },
+    /// A stacked leaf holds a value.
+    StackedNotZero { index: usize, value: W },
+    /// A stacked leaf has an ID.
+    StackedLabelled { index: usize, id: Id },
+    /// `ids_to_node_indexes` maps an ID to a node that is not labelled with it.
+    IdNotInverse { id: Id, index: usize },
+    /// `node_indexes_to_ids` labels a node with an ID that maps elsewhere.
+    LabelNotInverse { index: usize, id: Id },
+    /// A node labelled with an ID is the root, an internal node or past the
+    /// last node.
+    LabelledNotLeaf { index: usize, id: Id },
+    /// A labelled leaf holds 0, so its ID can never be drawn.
+    LabelledZero { index: usize, id: Id },
+    /// A leaf is neither labelled nor stacked, so it can never be reused.
+    Unaccounted { index: usize },
+}
+
+impl<Id, W: fmt::Debug> fmt::Display for Violation<Id, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::InvalidK { k } => write!(f, "K must be at least 2, got {k}"),
+            Violation::MissingRoot => f.write_str("the tree has no root node"),
+            Violation::WrongSum {
+                index,
+                value,
+                children_sum: Some(children_sum),
+            } => write!(
+                f,
+                "node {index} is not the sum of its children: {value:?} != {children_sum:?}"
+            ),
+            Violation::WrongSum { index, .. } => write!(
+                f,
+                "node {index} is not the sum of its children: the sum overflows"
+            ),
+            Violation::StackedNotLeaf { index } => write!(f, "stacked node {index} is not a leaf"),
+            Violation::StackedTwice { index } => {
+                write!(f, "node {index} is stacked several times")
+            }
+            Violation::StackedNotZero { index, value } => {
+                write!(f, "stacked node {index} is not vacant: it holds {value:?}")
+            }
+            Violation::StackedLabelled { index, .. } => {
+                write!(f, "stacked node {index} is not vacant: it has an ID")
+            }
+            Violation::IdNotInverse { index, .. } => {
+                write!(
+                    f,
+                    "an ID maps to node {index}, which is not labelled with it"
+                )
+            }
+            Violation::LabelNotInverse { index, .. } => {
+                write!(f, "the ID of node {index} maps to another node")
+            }
+            Violation::LabelledNotLeaf { index, .. } => {
+                write!(f, "labelled node {index} is not a leaf")
+            }
+            Violation::LabelledZero { index, .. } => write!(f, "labelled node {index} holds 0"),
+            Violation::Unaccounted { index } => {
+                write!(f, "leaf {index} is neither labelled nor stacked")
+            }
+        }
+    }
+}
+
+impl<Id, W> SortitionSumTree<Id, W>
+where
+    Id: Hash + Eq + Clone,
+    W: Weight,
+{
+    /**
+     *  @dev Check every invariant that `set` maintains: internal nodes hold the sum of their children, the two ID maps are exact inverses, stacked indexes are vacant leaves and IDs only label non-zero leaves.
+     *  @return violations Every broken invariant, or nothing if the tree is valid. Violations are grouped by invariant, in index order within a group.
+     *  `O(n)` where
+     *  `n` is the maximum number of nodes ever appended.
+     */
+    pub fn validate(&self) -> Vec<Violation<Id, W>> {
+        if self.k < 2 {
+            return vec![Violation::InvalidK { k: self.k }];
+        }
+        if self.nodes.is_empty() {
+            return vec![Violation::MissingRoot];
+        }
+        let mut violations = Vec::new();
+        let start_index = self.leaf_start_index();
+        let is_leaf = |index: usize| index != 0 && index >= start_index && index < self.nodes.len();
+
+        // The root is checked even when it is the only node, as it then has
+        // no children and must hold 0.
+        for index in 0..start_index.max(1) {
+            let first_child = self.k * index + 1;
+            let last_child = first_child.saturating_add(self.k).min(self.nodes.len());
+            let children_sum = self.nodes[first_child..last_child]
+                .iter()
+                .try_fold(W::zero(), |sum, &child| sum.checked_add(child));
+            if children_sum != Some(self.nodes[index]) {
+                violations.push(Violation::WrongSum {
+                    index,
+                    value: self.nodes[index],
+                    children_sum,
+                });
+            }
+        }
+
+        let mut stacked = vec![false; self.nodes.len()];
+        for &index in &self.stack {
+            if !is_leaf(index) {
+                violations.push(Violation::StackedNotLeaf { index });
+                continue;
+            }
+            if stacked[index] {
+                violations.push(Violation::StackedTwice { index });
+                continue;
+            }
+            stacked[index] = true;
+            if !self.nodes[index].is_zero() {
+                violations.push(Violation::StackedNotZero {
+                    index,
+                    value: self.nodes[index],
+                });
+            }
+            if let Some(id) = self.node_indexes_to_ids.get(&index) {
+                violations.push(Violation::StackedLabelled {
+                    index,
+                    id: id.clone(),
+                });
+            }
+        }
+
+        let mut ids: Vec<(usize, &Id)> = self
+            .ids_to_node_indexes
+            .iter()
+            .map(|(id, &index)| (index, id))
+            .collect();
+        ids.sort_unstable_by_key(|&(index, _)| index);
+        for &(index, id) in &ids {
+            if self.node_indexes_to_ids.get(&index) != Some(id) {
+                violations.push(Violation::IdNotInverse {
+                    id: id.clone(),
+                    index,
+                });
+            }
+        }
+        let mut labels: Vec<(usize, &Id)> = self
+            .node_indexes_to_ids
+            .iter()
+            .map(|(&index, id)| (index, id))
+            .collect();
+        labels.sort_unstable_by_key(|&(index, _)| index);
+        for &(index, id) in &labels {
+            if self.ids_to_node_indexes.get(id) != Some(&index) {
+                violations.push(Violation::LabelNotInverse {
+                    index,
+                    id: id.clone(),
+                });
+            }
+        }
+
+        for &(index, id) in &labels {
+            if !is_leaf(index) {
+                violations.push(Violation::LabelledNotLeaf {
+                    index,
+                    id: id.clone(),
+                });
+            } else if self.nodes[index].is_zero() {
+                violations.push(Violation::LabelledZero {
+                    index,
+                    id: id.clone(),
+                });
+            }
+        }
+
+        // A lone root is a leaf too, but holds no ID until the first `set`.
+        if self.nodes.len() > 1 {
+            violations.extend(
+                (start_index..self.nodes.len())
+                    .filter(|index| {
+                        !stacked[*index] && !self.node_indexes_to_ids.contains_key(index)
+                    })
+                    .map(|index| Violation::Unaccounted { index }),
+            );
+        }
+        violations
+    }
+}
+
+// Corrupting a tree takes the raw writes, which are private to the crate.
+#[cfg(test)]
+mod tests {
+    use super::Violation;
+    use crate::sortition_sum_tree::SortitionSumTree;
+    use crate::store::raw::RawNodeStore;
+    use crate::store::NodeStore;
+
+    /// Leaves 3 to 6 hold IDs 3, 1, 4 and 2, with 25 each.
+    fn four_leaves() -> SortitionSumTree<u64, u64> {
+        let mut tree = SortitionSumTree::new(2).unwrap();
+        for id in 1..=4 {
+            tree.set(25, id).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn wrong_sum_test() {
+        let mut tree = four_leaves();
+        tree.set_node(1, 99);
+        assert_eq!(
+            tree.validate(),
+            vec![
+                Violation::WrongSum {
+                    index: 0,
+                    value: 100,
+                    children_sum: Some(149),
+                },
+                Violation::WrongSum {
+                    index: 1,
+                    value: 99,
+                    children_sum: Some(50),
+                },
+            ]
+        );
+
+        let mut tree: SortitionSumTree<u64, u64> = SortitionSumTree::new(2).unwrap();
+        tree.set_node(0, 5);
+        assert_eq!(
+            tree.validate(),
+            vec![Violation::WrongSum {
+                index: 0,
+                value: 5,
+                children_sum: Some(0),
+            }]
+        );
+
+        let mut tree = four_leaves();
+        tree.set_node(3, u64::MAX);
+        assert_eq!(
+            tree.validate()[0],
+            Violation::WrongSum {
+                index: 1,
+                value: 50,
+                children_sum: None,
+            }
+        );
+    }
+
+    #[test]
+    fn stack_violations_test() {
+        let mut tree = four_leaves();
+        tree.push_vacant(1);
+        tree.push_vacant(3);
+        tree.push_vacant(7);
+        assert_eq!(
+            tree.validate(),
+            vec![
+                Violation::StackedNotLeaf { index: 1 },
+                Violation::StackedNotZero {
+                    index: 3,
+                    value: 25,
+                },
+                Violation::StackedLabelled { index: 3, id: 3 },
+                Violation::StackedNotLeaf { index: 7 },
+            ]
+        );
+
+        let mut tree = four_leaves();
+        tree.set(0, 3).unwrap();
+        tree.push_vacant(3);
+        assert_eq!(tree.validate(), vec![Violation::StackedTwice { index: 3 }]);
+    }
+
+    #[test]
+    fn id_violations_test() {
+        let mut tree = four_leaves();
+        // Leaf 5 is relabelled with ID 1: ID 4 still maps to it, and leaf 4 still names ID 1.
+        tree.link(5, 1);
+        assert_eq!(
+            tree.validate(),
+            vec![
+                Violation::IdNotInverse { id: 4, index: 5 },
+                Violation::LabelNotInverse { index: 4, id: 1 },
+            ]
+        );
+
+        let mut tree = four_leaves();
+        tree.link(2, 9);
+        tree.unlink(6);
+        tree.set_node(5, 0);
+        tree.set_node(2, 25);
+        tree.set_node(0, 75);
+        assert_eq!(
+            tree.validate(),
+            vec![
+                Violation::LabelledNotLeaf { index: 2, id: 9 },
+                Violation::LabelledZero { index: 5, id: 4 },
+                Violation::Unaccounted { index: 6 },
+            ]
+        );
+    }
+}
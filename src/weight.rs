@@ -0,0 +1,89 @@
+use std::fmt::Debug;
+
+pub use primitive_types::U256;
+
+/// The value type stored in the nodes of a tree.
+///
+/// Internal nodes hold the sum of their children, so every operation that
+/// can leave the representable range is checked.
+pub trait Weight: Copy + Ord + Debug {
+    /// The additive identity, held by vacant leaves and empty trees.
+    fn zero() -> Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+
+    /// `self % rhs`. `rhs` is never zero.
+    fn modulo(self, rhs: Self) -> Self;
+
+    /// Widens the value to 256 bits, the width of Solidity's `uint`.
+    fn into_u256(self) -> U256;
+
+    /// Narrows a 256-bit value, or returns `None` if it does not fit.
+    fn from_u256(value: U256) -> Option<Self>;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::zero()
+    }
+}
+
+macro_rules! impl_weight {
+    ($($t:ty),*) => {
+        $(
+            impl Weight for $t {
+                fn zero() -> Self {
+                    0
+                }
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, rhs)
+                }
+
+                fn modulo(self, rhs: Self) -> Self {
+                    self % rhs
+                }
+
+                fn into_u256(self) -> U256 {
+                    U256::from(self)
+                }
+
+                fn from_u256(value: U256) -> Option<Self> {
+                    <$t>::try_from(value).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_weight!(u64, u128);
+
+impl Weight for U256 {
+    fn zero() -> Self {
+        U256::zero()
+    }
+
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        U256::checked_add(self, rhs)
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        U256::checked_sub(self, rhs)
+    }
+
+    fn modulo(self, rhs: Self) -> Self {
+        self % rhs
+    }
+
+    fn into_u256(self) -> U256 {
+        self
+    }
+
+    fn from_u256(value: U256) -> Option<Self> {
+        Some(value)
+    }
+}
@@ -0,0 +1,225 @@
+use proptest::prelude::*;
+use sortition_sum_tree::{SnapshotId, SortitionError, SortitionSumTrees};
+
+/// Checks that a checkpoint answers like the copy of the tree taken with it.
+fn assert_matches(trees: &SortitionSumTrees, snapshot: SnapshotId, copy: &SortitionSumTrees) {
+    let total = copy.tree(&1).unwrap().total();
+    for number in 0..total {
+        assert_eq!(
+            trees.draw_at(&1, snapshot, number),
+            copy.draw(&1, number),
+            "number {number}"
+        );
+    }
+    for id in 0..20 {
+        assert_eq!(
+            trees.stake_of_at(&1, snapshot, &id),
+            copy.stake_of(&1, &id),
+            "id {id}"
+        );
+    }
+}
+
+/// Removals, updates and inserts that move a leaf down, with `checkpoint`
+/// called twice along the way.
+fn evolve(trees: &mut SortitionSumTrees, mut checkpoint: impl FnMut(&mut SortitionSumTrees)) {
+    trees.create_tree(1, 3).unwrap();
+    for id in 1..=4 {
+        trees.set(&1, id * 10, id).unwrap();
+    }
+    checkpoint(trees);
+    trees.set(&1, 0, 2).unwrap();
+    trees.set(&1, 5, 1).unwrap();
+    trees.set(&1, 50, 5).unwrap();
+    trees.set(&1, 60, 6).unwrap();
+    trees.set(&1, 70, 7).unwrap();
+    checkpoint(trees);
+    trees
+        .set_many(&1, [(3, 0), (2, 20), (8, 80), (5, 0)])
+        .unwrap();
+    trees.transfer_stake(&1, 7, 9, 30).unwrap();
+}
+
+#[test]
+fn checkpoint_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    let mut copies = Vec::new();
+    evolve(&mut trees, |trees| {
+        copies.push((trees.checkpoint(&1).unwrap(), trees.clone()))
+    });
+    let [(first, first_copy), (second, second_copy)] = &copies[..] else {
+        panic!("two checkpoints");
+    };
+    assert_eq!(*first, SnapshotId(0));
+    assert_eq!(*second, SnapshotId(1));
+
+    assert_matches(&trees, *first, first_copy);
+    assert_matches(&trees, *second, second_copy);
+    assert_eq!(trees.stake_of_at(&1, *first, &2), Ok(20));
+    assert_eq!(trees.stake_of_at(&1, *second, &2), Ok(0));
+    assert_eq!(trees.stake_of_at(&1, *second, &8), Ok(0));
+    assert_eq!(trees.stake_of(&1, &8), Ok(80));
+
+    // Checkpointing does not change the current tree.
+    let mut unchecked: SortitionSumTrees = SortitionSumTrees::new();
+    evolve(&mut unchecked, |_| {});
+    let (tree, unchecked_tree) = (trees.tree(&1).unwrap(), unchecked.tree(&1).unwrap());
+    assert_eq!(tree.nodes(), unchecked_tree.nodes());
+    assert_eq!(tree.stack(), unchecked_tree.stack());
+    assert!(tree.iter().eq(unchecked_tree.iter()));
+    assert_eq!(trees.validate(&1), Ok(vec![]));
+}
+
+#[test]
+fn checkpoint_large_tree_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    for id in 0..1_500 {
+        trees.set(&1, id % 7 + 1, id).unwrap();
+    }
+    let snapshot = trees.checkpoint(&1).unwrap();
+    let copy = trees.clone();
+    for id in (0..1_500).step_by(3) {
+        trees.set(&1, 0, id).unwrap();
+    }
+    for id in 1_500..2_500 {
+        trees.set(&1, 2, id).unwrap();
+    }
+    let total = copy.tree(&1).unwrap().total();
+    for number in (0..total).step_by(7) {
+        assert_eq!(trees.draw_at(&1, snapshot, number), copy.draw(&1, number));
+    }
+    for id in (0..2_500).step_by(11) {
+        assert_eq!(trees.stake_of_at(&1, snapshot, &id), copy.stake_of(&1, &id));
+    }
+}
+
+#[test]
+fn checkpoint_errors_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    assert_eq!(trees.checkpoint(&1), Err(SortitionError::TreeNotFound));
+    trees.create_tree(1, 2).unwrap();
+    assert_eq!(
+        trees.draw_at(&1, SnapshotId(0), 0),
+        Err(SortitionError::SnapshotNotFound)
+    );
+    let snapshot = trees.checkpoint(&1).unwrap();
+    trees.set(&1, 10, 1).unwrap();
+    assert_eq!(
+        trees.draw_at(&1, snapshot, 0),
+        Err(SortitionError::EmptyTree)
+    );
+    assert_eq!(trees.stake_of_at(&1, snapshot, &1), Ok(0));
+    assert_eq!(
+        trees.stake_of_at(&1, SnapshotId(1), &1),
+        Err(SortitionError::SnapshotNotFound)
+    );
+    assert_eq!(
+        trees.draw_at(&1, SnapshotId(u64::MAX), 0),
+        Err(SortitionError::SnapshotNotFound)
+    );
+}
+
+#[test]
+fn release_checkpoint_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    let mut copies = Vec::new();
+    evolve(&mut trees, |trees| {
+        copies.push((trees.checkpoint(&1).unwrap(), trees.clone()))
+    });
+    let before = trees.clone();
+    trees.begin().unwrap();
+    trees.release_checkpoint(&1, SnapshotId(0)).unwrap();
+    trees.set(&1, 0, 8).unwrap();
+    trees.set(&1, 15, 10).unwrap();
+    trees.checkpoint(&1).unwrap();
+    trees.rollback().unwrap();
+    assert_eq!(trees, before);
+
+    trees.release_checkpoint(&1, SnapshotId(0)).unwrap();
+    assert_eq!(
+        trees.draw_at(&1, SnapshotId(0), 0),
+        Err(SortitionError::SnapshotNotFound)
+    );
+    assert_eq!(
+        trees.release_checkpoint(&1, SnapshotId(0)),
+        Err(SortitionError::SnapshotNotFound)
+    );
+    assert_eq!(
+        trees.release_checkpoint(&1, SnapshotId(2)),
+        Err(SortitionError::SnapshotNotFound)
+    );
+    assert_eq!(
+        trees.release_checkpoint(&2, SnapshotId(0)),
+        Err(SortitionError::TreeNotFound)
+    );
+    assert_matches(&trees, copies[1].0, &copies[1].1);
+    // IDs are not reused.
+    assert_eq!(trees.checkpoint(&1), Ok(SnapshotId(2)));
+}
+
+proptest! {
+    #[test]
+    fn checkpoint_matches_copy_test(
+        k in 2..=5usize,
+        batches in prop::collection::vec(
+            prop::collection::vec((0..20u128, 0..30u128), 0..25),
+            1..6,
+        ),
+    ) {
+        let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+        trees.create_tree(1, k).unwrap();
+        let mut copies = Vec::new();
+        for batch in batches {
+            for (id, value) in batch {
+                trees.set(&1, value, id).unwrap();
+            }
+            copies.push((trees.checkpoint(&1).unwrap(), trees.clone()));
+        }
+        trees.set_many(&1, (0..20).map(|id| (id, id % 3))).unwrap();
+        for (snapshot, copy) in &copies {
+            assert_matches(&trees, *snapshot, copy);
+        }
+    }
+
+    #[test]
+    fn release_checkpoint_matches_copy_test(
+        k in 2..=5usize,
+        batches in prop::collection::vec(
+            prop::collection::vec((0..20u128, 0..30u128), 0..25),
+            1..6,
+        ),
+        released in prop::collection::vec(any::<bool>(), 6),
+    ) {
+        let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+        trees.create_tree(1, k).unwrap();
+        let mut copies = Vec::new();
+        for (batch, release) in batches.into_iter().zip(released) {
+            for (id, value) in batch {
+                trees.set(&1, value, id).unwrap();
+            }
+            // Each checkpoint is released once the next batch is written.
+            if let Some((snapshot, _, true)) = copies.last() {
+                trees.release_checkpoint(&1, *snapshot).unwrap();
+            }
+            copies.push((trees.checkpoint(&1).unwrap(), trees.clone(), release));
+        }
+        trees.set_many(&1, (0..20).map(|id| (id, id % 3))).unwrap();
+        if let Some((snapshot, _, true)) = copies.last() {
+            trees.release_checkpoint(&1, *snapshot).unwrap();
+        }
+        let last = (trees.checkpoint(&1).unwrap(), trees.clone());
+        trees.set_many(&1, (0..20).map(|id| (id, id % 5))).unwrap();
+        for (snapshot, copy, release) in &copies {
+            if *release {
+                prop_assert_eq!(
+                    trees.stake_of_at(&1, *snapshot, &0),
+                    Err(SortitionError::SnapshotNotFound)
+                );
+            } else {
+                assert_matches(&trees, *snapshot, copy);
+            }
+        }
+        assert_matches(&trees, last.0, &last.1);
+    }
+}
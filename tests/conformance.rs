@@ -0,0 +1,277 @@
+//! Replays the operation scripts in `tests/vectors` and checks every result
+//! against the one recorded from the Solidity `SortitionSumTreeFactory`.
+//!
+//! The vectors are produced by `tests/vectors/generate.py`, which runs each
+//! script against a statement-by-statement transliteration of the contract.
+//! `tests/vectors/evm/replay.py` checks them against the contract itself on
+//! an EVM and writes what the EVM returned to the `evm-*.json` vectors, which
+//! are replayed here as well and must be present.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use sortition_sum_tree::{SortitionError, SortitionSumTrees, U256};
+
+type Trees = SortitionSumTrees<U256, U256, U256>;
+
+#[derive(Deserialize)]
+struct Script {
+    name: String,
+    steps: Vec<Step>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+enum Step {
+    CreateTree {
+        key: String,
+        k: usize,
+        expect_revert: Option<String>,
+    },
+    Set {
+        key: String,
+        value: String,
+        id: String,
+        expect_revert: Option<String>,
+    },
+    StakeOf {
+        key: String,
+        id: String,
+        expected: String,
+    },
+    Draw {
+        key: String,
+        drawn_number: String,
+        expected: Option<String>,
+        expect_revert: Option<String>,
+    },
+    DrawFromSeed {
+        key: String,
+        seed: String,
+        dispute_id: String,
+        nonce: u64,
+        expected: Option<String>,
+        expect_revert: Option<String>,
+    },
+    QueryLeafs {
+        key: String,
+        cursor: usize,
+        count: usize,
+        expected: LeafsResult,
+    },
+    CheckTree {
+        key: String,
+        nodes: Vec<String>,
+        stack: Vec<usize>,
+        ids: Vec<(usize, String)>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LeafsResult {
+    start_index: usize,
+    values: Vec<String>,
+    has_more: bool,
+}
+
+fn uint(value: &str) -> U256 {
+    U256::from_dec_str(value).unwrap()
+}
+
+fn bytes32(hex: &str) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+    }
+    bytes
+}
+
+/// The error `SortitionSumTrees` returns for a call the Solidity library
+/// reverts on: the `require` messages map to their variant, and an invalid
+/// opcode to `TreeNotFound` when the tree was never created (modulo by zero
+/// with `K == 0`) or `EmptyTree` otherwise (modulo by a zero root).
+fn expected_error(trees: &Trees, key: &str, revert: &str) -> SortitionError {
+    match revert {
+        "Tree already exists." => SortitionError::TreeAlreadyExists,
+        "K must be greater than one." => SortitionError::InvalidK,
+        "invalid opcode" if trees.tree(&uint(key)).is_err() => SortitionError::TreeNotFound,
+        "invalid opcode" => SortitionError::EmptyTree,
+        revert => panic!("unmapped revert {revert:?}"),
+    }
+}
+
+/// Checks a result against the expected outcome of the Solidity call.
+fn check<T>(result: Result<T, SortitionError>, expected: Option<SortitionError>, context: &str)
+where
+    T: std::fmt::Debug,
+{
+    match expected {
+        None => assert!(result.is_ok(), "{context}: unexpected {result:?}"),
+        Some(error) => assert_eq!(result.unwrap_err(), error, "{context}"),
+    }
+}
+
+fn replay(script: &Script) {
+    let mut trees = Trees::new();
+    for (i, step) in script.steps.iter().enumerate() {
+        let context = format!("{} step {}", script.name, i);
+        match step {
+            Step::CreateTree {
+                key,
+                k,
+                expect_revert,
+            } => {
+                let expected = expect_revert
+                    .as_deref()
+                    .map(|revert| expected_error(&trees, key, revert));
+                check(trees.create_tree(uint(key), *k), expected, &context)
+            }
+            Step::Set {
+                key,
+                value,
+                id,
+                expect_revert,
+            } => {
+                let expected = expect_revert
+                    .as_deref()
+                    .map(|revert| expected_error(&trees, key, revert));
+                check(
+                    trees.set(&uint(key), uint(value), uint(id)),
+                    expected,
+                    &context,
+                )
+            }
+            Step::StakeOf { key, id, expected } => {
+                assert_eq!(
+                    trees.stake_of(&uint(key), &uint(id)),
+                    Ok(uint(expected)),
+                    "{context}"
+                );
+            }
+            Step::Draw {
+                key,
+                drawn_number,
+                expected,
+                expect_revert,
+            } => {
+                let result = trees.draw(&uint(key), uint(drawn_number));
+                if let Some(expected) = expected {
+                    assert_eq!(result, Ok(uint(expected)), "{context}");
+                }
+                let expected = expect_revert
+                    .as_deref()
+                    .map(|revert| expected_error(&trees, key, revert));
+                check(result, expected, &context);
+            }
+            Step::DrawFromSeed {
+                key,
+                seed,
+                dispute_id,
+                nonce,
+                expected,
+                expect_revert,
+            } => {
+                let result =
+                    trees.draw_from_seed(&uint(key), bytes32(seed), uint(dispute_id), *nonce);
+                if let Some(expected) = expected {
+                    assert_eq!(result, Ok(uint(expected)), "{context}");
+                }
+                let expected = expect_revert
+                    .as_deref()
+                    .map(|revert| expected_error(&trees, key, revert));
+                check(result, expected, &context);
+            }
+            Step::QueryLeafs {
+                key,
+                cursor,
+                count,
+                expected,
+            } => {
+                // Solidity returns every node of the window, vacant leaves
+                // as 0 values; `query_leaves` returns the labelled ones.
+                let tree = trees.tree(&uint(key)).unwrap();
+                let nodes = tree.nodes();
+                let first = expected.start_index + cursor;
+                let last = (first + count).min(nodes.len());
+                let values: Vec<U256> = expected.values.iter().map(|value| uint(value)).collect();
+                assert_eq!(&nodes[first..last], &values[..], "{context}: values");
+                assert_eq!(
+                    first + count < nodes.len(),
+                    expected.has_more,
+                    "{context}: has more"
+                );
+
+                let labelled: Vec<(U256, U256)> = (first..last)
+                    .filter_map(|index| tree.id_at(index).map(|id| (*id, nodes[index])))
+                    .collect();
+                let page = trees
+                    .query_leaves(&uint(key), *cursor, labelled.len())
+                    .unwrap();
+                assert_eq!(page.start_index, expected.start_index, "{context}");
+                assert_eq!(page.entries, labelled, "{context}: entries");
+                let next = (last..nodes.len()).find(|index| tree.id_at(*index).is_some());
+                assert_eq!(
+                    page.next_cursor,
+                    next.map(|index| index - expected.start_index),
+                    "{context}: next cursor"
+                );
+            }
+            Step::CheckTree {
+                key,
+                nodes,
+                stack,
+                ids,
+            } => {
+                let tree = trees.tree(&uint(key)).unwrap();
+                let nodes: Vec<U256> = nodes.iter().map(|node| uint(node)).collect();
+                assert_eq!(tree.nodes(), &nodes[..], "{context}: nodes");
+                assert_eq!(tree.stack(), &stack[..], "{context}: stack");
+                let ids: HashMap<usize, U256> =
+                    ids.iter().map(|(index, id)| (*index, uint(id))).collect();
+                for index in 0..nodes.len() {
+                    assert_eq!(
+                        tree.id_at(index),
+                        ids.get(&index),
+                        "{context}: ID at {index}"
+                    );
+                    if let Some(id) = ids.get(&index) {
+                        assert_eq!(tree.node_index_of(id), Some(index), "{context}: {id}");
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Replays the vectors of `tests/vectors` whose file name does or does not
+/// start with `evm-`, returning how many were replayed.
+fn replay_vectors(evm: bool) -> usize {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors");
+    let mut replayed = 0;
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy();
+        if name.ends_with(".json") && name.starts_with("evm-") == evm {
+            let script: Script = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            replay(&script);
+            replayed += 1;
+        }
+    }
+    replayed
+}
+
+#[test]
+fn conformance_vectors_test() {
+    assert!(replay_vectors(false) > 0, "no vectors found");
+}
+
+#[test]
+fn evm_conformance_vectors_test() {
+    assert!(
+        replay_vectors(true) > 0,
+        "no EVM vectors found, run `tests/vectors/evm/replay.py --write`"
+    );
+}
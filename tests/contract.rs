@@ -0,0 +1,145 @@
+#![cfg(feature = "near")]
+
+use near_sdk::json_types::{U128, U64};
+use near_sdk::test_utils::{accounts, VMContextBuilder};
+use near_sdk::testing_env;
+use sortition_sum_tree::contract::{LeavesView, SortitionContract};
+use sortition_sum_tree::SortitionError;
+
+fn as_caller(index: usize) {
+    testing_env!(VMContextBuilder::new()
+        .predecessor_account_id(accounts(index))
+        .build());
+}
+
+fn contract() -> SortitionContract {
+    as_caller(0);
+    let mut contract = SortitionContract::new(accounts(0));
+    contract.create_tree("court".to_string(), U64(2)).unwrap();
+    for index in 1..=4 {
+        contract
+            .set("court".to_string(), U128(25), accounts(index))
+            .unwrap();
+    }
+    contract
+}
+
+#[test]
+fn contract_test() {
+    let mut contract = contract();
+    assert_eq!(contract.owner_id(), accounts(0));
+    assert_eq!(
+        contract.stake_of("court".to_string(), accounts(2)),
+        Ok(U128(25))
+    );
+    assert_eq!(
+        contract.draw("court".to_string(), U128(20)),
+        Ok(accounts(3))
+    );
+    assert_eq!(
+        contract.draw("court".to_string(), U128(80)),
+        Ok(accounts(2))
+    );
+
+    contract
+        .set("court".to_string(), U128(0), accounts(1))
+        .unwrap();
+    assert_eq!(
+        contract.query_leaves("court".to_string(), U64(0), U64(2)),
+        Ok(LeavesView {
+            start_index: U64(3),
+            entries: vec![(accounts(3), U128(25)), (accounts(4), U128(25))],
+            next_cursor: Some(U64(3)),
+        })
+    );
+    let page = contract
+        .query_leaves("court".to_string(), U64(3), U64(2))
+        .unwrap();
+    assert_eq!(
+        near_sdk::serde_json::to_value(page).unwrap(),
+        near_sdk::serde_json::json!({
+            "start_index": "3",
+            "entries": [["charlie", "25"]],
+            "next_cursor": null,
+        })
+    );
+}
+
+#[test]
+fn contract_errors_test() {
+    let mut contract = contract();
+    assert_eq!(
+        contract.stake_of("appeals".to_string(), accounts(1)),
+        Err(SortitionError::TreeNotFound)
+    );
+    assert_eq!(
+        contract.create_tree("court".to_string(), U64(2)),
+        Err(SortitionError::TreeAlreadyExists)
+    );
+    contract.create_tree("appeals".to_string(), U64(4)).unwrap();
+    assert_eq!(
+        contract.draw("appeals".to_string(), U128(7)),
+        Err(SortitionError::EmptyTree)
+    );
+}
+
+#[test]
+fn contract_set_many_test() {
+    let mut contract = contract();
+    contract
+        .set_many(
+            "court".to_string(),
+            vec![
+                (accounts(1), U128(0)),
+                (accounts(5), U128(40)),
+                (accounts(2), U128(10)),
+            ],
+        )
+        .unwrap();
+    assert_eq!(
+        contract.stake_of("court".to_string(), accounts(1)),
+        Ok(U128(0))
+    );
+    assert_eq!(
+        contract.stake_of("court".to_string(), accounts(5)),
+        Ok(U128(40))
+    );
+    assert_eq!(
+        contract.set_many(
+            "court".to_string(),
+            vec![(accounts(1), U128(1)), (accounts(2), U128(u128::MAX))],
+        ),
+        Err(SortitionError::Overflow)
+    );
+    assert_eq!(
+        contract.stake_of("court".to_string(), accounts(1)),
+        Ok(U128(0))
+    );
+}
+
+#[test]
+fn contract_state_round_trip_test() {
+    let contract = contract();
+    let bytes = borsh::BorshSerialize::try_to_vec(&contract).unwrap();
+    let loaded: SortitionContract = borsh::BorshDeserialize::try_from_slice(&bytes).unwrap();
+    assert_eq!(
+        loaded.draw("court".to_string(), U128(60)),
+        contract.draw("court".to_string(), U128(60))
+    );
+}
+
+#[test]
+#[should_panic(expected = "Only the owner can modify the trees")]
+fn set_requires_owner_test() {
+    let mut contract = contract();
+    as_caller(1);
+    let _ = contract.set("court".to_string(), U128(1_000), accounts(1));
+}
+
+#[test]
+#[should_panic(expected = "Only the owner can modify the trees")]
+fn create_tree_requires_owner_test() {
+    let mut contract = contract();
+    as_caller(1);
+    let _ = contract.create_tree("appeals".to_string(), U64(4));
+}
@@ -0,0 +1,84 @@
+use std::collections::HashSet;
+
+use sortition_sum_tree::{keccak256, SortitionError, SortitionSumTrees, U256};
+
+fn trees() -> SortitionSumTrees {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 3).unwrap();
+    for id in 1..=12 {
+        trees.set(&1, id * 10, id).unwrap();
+    }
+    trees.set(&1, 0, 5).unwrap();
+    trees
+}
+
+#[test]
+fn draw_excluding_test() {
+    let trees = trees();
+    let excluded: HashSet<u128> = [2, 3, 9, 12, 42].into_iter().collect();
+    // Excluding IDs draws exactly like a tree where they hold 0.
+    let mut reduced = trees.clone();
+    for &id in &excluded {
+        reduced.set(&1, 0, id).unwrap();
+    }
+    for i in 0..200u32 {
+        let seed = keccak256(&i.to_be_bytes());
+        let id = trees
+            .draw_excluding(&1, seed, U256::zero(), &excluded)
+            .unwrap();
+        assert!(!excluded.contains(&id));
+        assert_eq!(reduced.draw_from_seed(&1, seed, U256::zero(), 0), Ok(id));
+    }
+    assert_eq!(
+        trees.draw_excluding(&1, [7; 32], U256::zero(), &HashSet::new()),
+        trees.draw_from_seed(&1, [7; 32], U256::zero(), 0)
+    );
+}
+
+#[test]
+fn draw_filtered_test() {
+    let trees = trees();
+    let excluded: HashSet<u128> = (1..=12).filter(|id| id % 4 == 0).collect();
+    for i in 0..200u32 {
+        let seed = keccak256(&i.to_be_bytes());
+        let mut calls = 0;
+        let id = trees
+            .draw_filtered(&1, seed, U256::zero(), |id| {
+                calls += 1;
+                id % 4 != 0
+            })
+            .unwrap();
+        assert_eq!(calls, trees.len(&1).unwrap());
+        assert_eq!(
+            trees.draw_excluding(&1, seed, U256::zero(), &excluded),
+            Ok(id)
+        );
+    }
+}
+
+#[test]
+fn draw_excluding_errors_test() {
+    let mut trees = trees();
+    let everyone: HashSet<u128> = trees.ids(&1).unwrap().copied().collect();
+    assert_eq!(
+        trees.draw_excluding(&1, [0; 32], U256::zero(), &everyone),
+        Err(SortitionError::NoEligibleId)
+    );
+    assert_eq!(
+        trees.draw_filtered(&1, [0; 32], U256::zero(), |_| false),
+        Err(SortitionError::NoEligibleId)
+    );
+    assert_eq!(
+        trees.draw_excluding(&2, [0; 32], U256::zero(), &everyone),
+        Err(SortitionError::TreeNotFound)
+    );
+    trees.create_tree(2, 2).unwrap();
+    assert_eq!(
+        trees.draw_filtered(&2, [0; 32], U256::zero(), |_| true),
+        Err(SortitionError::EmptyTree)
+    );
+    assert_eq!(
+        SortitionError::NoEligibleId.to_string(),
+        "no eligible ID in the tree"
+    );
+}
@@ -0,0 +1,101 @@
+use sortition_sum_tree::{drawn_number, keccak256, SortitionError, SortitionSumTrees, U256};
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn four_equal_stakes() -> SortitionSumTrees {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, 25, 1).unwrap();
+    trees.set(&1, 25, 2).unwrap();
+    trees.set(&1, 25, 3).unwrap();
+    trees.set(&1, 25, 4).unwrap();
+    trees
+}
+
+#[test]
+fn keccak256_test() {
+    assert_eq!(
+        hex(&keccak256(b"")),
+        "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+    );
+    // keccak256(abi.encodePacked(uint256(1)))
+    let mut one = [0u8; 32];
+    one[31] = 1;
+    assert_eq!(
+        hex(&keccak256(&one)),
+        "b10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6"
+    );
+}
+
+#[test]
+fn drawn_number_test() {
+    // keccak256(abi.encodePacked(uint256(0), uint256(0), uint256(0)))
+    assert_eq!(
+        drawn_number(&[0; 32], U256::zero(), 0),
+        U256::from_str_radix(
+            "46700b4d40ac5c35af2c22dda2787a91eb567b06c924a8fb8ae9a05b20c08c21",
+            16
+        )
+        .unwrap()
+    );
+    // keccak256(abi.encodePacked(RN, disputeID, nonce)), as in `drawJurors`.
+    let mut packed = [0u8; 96];
+    packed[..32].copy_from_slice(&[7; 32]);
+    packed[63] = 42;
+    packed[95] = 3;
+    assert_eq!(
+        drawn_number(&[7; 32], U256::from(42), 3),
+        U256::from_big_endian(&keccak256(&packed))
+    );
+    assert_eq!(
+        drawn_number(&[7; 32], U256::from(42), 3),
+        U256::from_str_radix(
+            "19acee712273c3d7ae4dd534afef0e3eefb9051a9cbe0e50439817dc0c7a5841",
+            16
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn draw_from_seed_test() {
+    let trees = four_equal_stakes();
+    // 0x4670...8c21 % 100 == 49, which falls in the range of ID 1.
+    assert_eq!(trees.draw_from_seed(&1, [0; 32], U256::zero(), 0), Ok(1));
+    for nonce in 0..32 {
+        let number = drawn_number(&[9; 32], U256::from(5), nonce);
+        let reduced = (number % U256::from(100)).as_u128();
+        assert_eq!(
+            trees.draw_from_seed(&1, [9; 32], U256::from(5), nonce),
+            trees.draw(&1, reduced)
+        );
+        assert_eq!(trees.draw_u256(&1, number), trees.draw(&1, reduced));
+    }
+    // The dispute ID is part of the number.
+    assert_ne!(
+        drawn_number(&[9; 32], U256::from(5), 0),
+        drawn_number(&[9; 32], U256::from(6), 0)
+    );
+}
+
+#[test]
+fn draw_u256_reduces_in_256_bits_test() {
+    let trees = four_equal_stakes();
+    // 2^128 + 20 would be truncated to 20 by a u128 cast; in 256 bits it is
+    // (2^128 + 20) % 100 = 76, which falls in the range of ID 2.
+    let number = (U256::one() << 128) + U256::from(20);
+    let expected = (number % U256::from(100)).as_u128();
+    assert_eq!(expected, 76);
+    assert_eq!(trees.draw_u256(&1, number), trees.draw(&1, 76));
+    assert_eq!(trees.draw_u256(&1, number), Ok(2));
+
+    let mut empty: SortitionSumTrees = SortitionSumTrees::new();
+    assert_eq!(
+        empty.draw_from_seed(&1, [0; 32], U256::zero(), 0),
+        Err(SortitionError::TreeNotFound)
+    );
+    empty.create_tree(1, 2).unwrap();
+    assert_eq!(empty.draw_u256(&1, number), Err(SortitionError::EmptyTree));
+}
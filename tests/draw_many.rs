@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use sortition_sum_tree::{keccak256, SnapshotId, SortitionError, SortitionSumTrees, U256};
+
+fn trees() -> SortitionSumTrees {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 3).unwrap();
+    for id in 1..=10 {
+        trees.set(&1, id * 10, id).unwrap();
+    }
+    trees.set(&1, 0, 4).unwrap();
+    trees.set(&1, 0, 7).unwrap();
+    trees
+}
+
+#[test]
+fn draw_many_test() {
+    let trees = trees();
+    let seed = keccak256(b"dispute 1");
+    let ids = trees.draw_many(&1, seed, U256::zero(), 50).unwrap();
+    assert_eq!(ids.len(), 50);
+    for (nonce, id) in ids.iter().enumerate() {
+        assert_eq!(
+            trees
+                .draw_from_seed(&1, seed, U256::zero(), nonce as u64)
+                .as_ref(),
+            Ok(id)
+        );
+    }
+    // With replacement, 50 draws among 8 IDs repeat some of them.
+    assert!(ids.iter().collect::<HashSet<_>>().len() < ids.len());
+    assert_eq!(trees.draw_many(&1, seed, U256::zero(), 0), Ok(vec![]));
+}
+
+#[test]
+fn draw_distinct_test() {
+    let trees = trees();
+    let before = trees.clone();
+    let seed = keccak256(b"dispute 1");
+
+    let ids = trees.draw_distinct(&1, seed, U256::zero(), 5).unwrap();
+    assert_eq!(trees, before, "the tree must not change");
+    assert_eq!(ids.len(), 5);
+    assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 5);
+    assert_eq!(trees.draw_from_seed(&1, seed, U256::zero(), 0), Ok(ids[0]));
+    // Each ID is drawn from the tree without the IDs before it.
+    let mut reduced = trees.clone();
+    for (nonce, &id) in ids.iter().enumerate() {
+        assert_eq!(
+            reduced.draw_from_seed(&1, seed, U256::zero(), nonce as u64),
+            Ok(id)
+        );
+        reduced.set(&1, 0, id).unwrap();
+    }
+    assert_eq!(trees.draw_distinct(&1, seed, U256::zero(), 5), Ok(ids));
+
+    // Asking for more IDs than the tree holds returns each of them once.
+    let all = trees.draw_distinct(&1, seed, U256::zero(), 20).unwrap();
+    assert_eq!(trees, before);
+    assert_eq!(
+        all.into_iter().collect::<HashSet<_>>(),
+        before.ids(&1).unwrap().copied().collect::<HashSet<_>>()
+    );
+}
+
+#[test]
+fn draw_distinct_checkpointed_test() {
+    let mut trees = trees();
+    let snapshot = trees.checkpoint(&1).unwrap();
+    let before = trees.clone();
+    let seed = keccak256(b"dispute 1");
+
+    let ids = trees.draw_distinct(&1, seed, U256::zero(), 5).unwrap();
+    assert_eq!(trees, before, "the history must not grow");
+    for id in &ids {
+        assert_eq!(trees.stake_of_at(&1, snapshot, id), before.stake_of(&1, id));
+    }
+    assert_eq!(trees.checkpoint(&1), Ok(SnapshotId(1)));
+}
+
+#[test]
+fn draw_many_errors_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    assert_eq!(
+        trees.draw_many(&1, [0; 32], U256::zero(), 1),
+        Err(SortitionError::TreeNotFound)
+    );
+    assert_eq!(
+        trees.draw_distinct(&1, [0; 32], U256::zero(), 1),
+        Err(SortitionError::TreeNotFound)
+    );
+    trees.create_tree(1, 2).unwrap();
+    assert_eq!(
+        trees.draw_many(&1, [0; 32], U256::zero(), 1),
+        Err(SortitionError::EmptyTree)
+    );
+    assert_eq!(
+        trees.draw_distinct(&1, [0; 32], U256::zero(), 1),
+        Err(SortitionError::EmptyTree)
+    );
+    assert_eq!(
+        trees.draw_distinct(&1, [0; 32], U256::zero(), 0),
+        Ok(vec![])
+    );
+}
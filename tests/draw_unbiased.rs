@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use sortition_sum_tree::{SortitionError, SortitionSumTrees};
+
+/// A deterministic splitmix64 byte stream, so the statistical tests never flake.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+/// Pearson's chi-squared statistic of `counts` against `weights`.
+fn chi_squared(counts: &HashMap<u128, u64>, weights: &[(u128, u128)], draws: u64) -> f64 {
+    let total: u128 = weights.iter().map(|(_, weight)| weight).sum();
+    weights
+        .iter()
+        .map(|(id, weight)| {
+            let expected = draws as f64 * *weight as f64 / total as f64;
+            let observed = *counts.get(id).unwrap_or(&0) as f64;
+            (observed - expected).powi(2) / expected
+        })
+        .sum()
+}
+
+#[test]
+fn draw_unbiased_is_exact_over_all_candidates_test() {
+    // total = 3 needs 2 random bits: candidates 0, 1 and 2 map to a leaf once
+    // each and 3 is rejected, so cycling through every candidate yields
+    // exactly the 1:2 ratio.
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, 1, 10).unwrap();
+    trees.set(&1, 2, 20).unwrap();
+    let mut next = 0u8;
+    let mut source = |buf: &mut [u8]| {
+        assert_eq!(buf.len(), 1);
+        buf[0] = next | 0b1111_1100;
+        next = (next + 1) % 4;
+    };
+    let mut counts: HashMap<u128, u64> = HashMap::new();
+    for _ in 0..3000 {
+        *counts
+            .entry(trees.draw_unbiased(&1, &mut source).unwrap())
+            .or_default() += 1;
+    }
+    assert_eq!(counts[&10], 1000);
+    assert_eq!(counts[&20], 2000);
+}
+
+#[test]
+fn draw_unbiased_is_uniform_test() {
+    let weights: Vec<(u128, u128)> = (1..=12).map(|id| (id, id * 7 + id % 3)).collect();
+    for k in [2, 3, 5] {
+        let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+        trees.create_tree(1, k).unwrap();
+        for &(id, weight) in &weights {
+            trees.set(&1, weight, id).unwrap();
+        }
+        let mut rng = SplitMix64(k as u64);
+        let mut source = |buf: &mut [u8]| rng.fill(buf);
+        let draws = 200_000;
+        let mut counts: HashMap<u128, u64> = HashMap::new();
+        for _ in 0..draws {
+            *counts
+                .entry(trees.draw_unbiased(&1, &mut source).unwrap())
+                .or_default() += 1;
+        }
+        // 11 degrees of freedom; 31.26 is the 0.001 critical value.
+        let statistic = chi_squared(&counts, &weights, draws);
+        assert!(statistic < 31.26, "K={k}: chi-squared {statistic}");
+    }
+}
+
+#[test]
+fn draw_unbiased_removes_modulo_bias_test() {
+    // With a single random byte, `draw` maps 256 numbers onto a total of 192:
+    // numbers 0..64 are hit twice, so ID 1 is drawn half of the time instead
+    // of a third.
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, 64, 1).unwrap();
+    trees.set(&1, 128, 2).unwrap();
+    let biased = (0..=255u128)
+        .filter(|&number| trees.draw(&1, number).unwrap() == 1)
+        .count();
+    assert_eq!(biased, 128);
+
+    let mut rng = SplitMix64(42);
+    let mut source = |buf: &mut [u8]| rng.fill(buf);
+    let draws = 90_000;
+    let mut counts: HashMap<u128, u64> = HashMap::new();
+    for _ in 0..draws {
+        *counts
+            .entry(trees.draw_unbiased(&1, &mut source).unwrap())
+            .or_default() += 1;
+    }
+    // 1 degree of freedom; 10.83 is the 0.001 critical value.
+    let statistic = chi_squared(&counts, &[(1, 64), (2, 128)], draws);
+    assert!(statistic < 10.83, "chi-squared {statistic}");
+}
+
+#[test]
+fn draw_unbiased_errors_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    let mut source = |buf: &mut [u8]| buf.fill(0);
+    assert_eq!(
+        trees.draw_unbiased(&1, &mut source),
+        Err(SortitionError::TreeNotFound)
+    );
+    trees.create_tree(1, 2).unwrap();
+    assert_eq!(
+        trees.draw_unbiased(&1, &mut source),
+        Err(SortitionError::EmptyTree)
+    );
+}
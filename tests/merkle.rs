@@ -0,0 +1,145 @@
+use proptest::prelude::*;
+use sortition_sum_tree::{keccak256, verify_draw_proof, SortitionError, SortitionSumTrees, U256};
+
+fn uint256(value: u128) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    U256::from(value).to_big_endian(&mut bytes);
+    bytes
+}
+
+fn trees() -> SortitionSumTrees {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 3).unwrap();
+    for id in 1..=8 {
+        trees.set(&1, id * 10, id).unwrap();
+    }
+    trees.set(&1, 0, 3).unwrap();
+    trees
+}
+
+#[test]
+fn state_root_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    assert_eq!(trees.state_root(&1), Ok(keccak256(&[0x01])));
+
+    trees.set(&1, 10, 1).unwrap();
+    trees.set(&1, 20, 2).unwrap();
+    let leaf = |id: u128, value: u128| {
+        let mut packed = vec![0x00];
+        packed.extend(uint256(value));
+        packed.extend(id.to_be_bytes());
+        keccak256(&packed)
+    };
+    let mut packed = vec![0x02];
+    packed.extend(leaf(1, 10));
+    packed.extend(uint256(10));
+    packed.extend(leaf(2, 20));
+    packed.extend(uint256(20));
+    let root = keccak256(&packed);
+    assert_eq!(trees.state_root(&1), Ok(root));
+
+    // The root follows every change, and only depends on the tree.
+    trees.set(&1, 15, 1).unwrap();
+    assert_ne!(trees.state_root(&1), Ok(root));
+    trees.set(&1, 10, 1).unwrap();
+    assert_eq!(trees.state_root(&1), Ok(root));
+    trees.set(&1, 0, 2).unwrap();
+    trees.set(&1, 20, 2).unwrap();
+    assert_eq!(trees.state_root(&1), Ok(root));
+
+    assert_eq!(trees.state_root(&2), Err(SortitionError::TreeNotFound));
+}
+
+#[test]
+fn draw_with_proof_test() {
+    let mut trees = trees();
+    let root = trees.state_root(&1).unwrap();
+    let total = trees.tree(&1).unwrap().total();
+    for number in 0..total + 5 {
+        let proof = trees.draw_with_proof(&1, number).unwrap();
+        assert_eq!(Ok(proof.id), trees.draw(&1, number));
+        assert_eq!(verify_draw_proof(&root, number, &proof), Ok(proof.id));
+    }
+}
+
+#[test]
+fn verify_draw_proof_errors_test() {
+    let mut trees = trees();
+    let root = trees.state_root(&1).unwrap();
+    let proof = trees.draw_with_proof(&1, 100).unwrap();
+    assert_eq!(verify_draw_proof(&root, 100, &proof), Ok(6));
+
+    let mut wrong_root = root;
+    wrong_root[0] ^= 1;
+    assert_eq!(
+        verify_draw_proof(&wrong_root, 100, &proof),
+        Err(SortitionError::InvalidProof)
+    );
+    // The number leads to another leaf.
+    assert_eq!(
+        verify_draw_proof(&root, 0, &proof),
+        Err(SortitionError::InvalidProof)
+    );
+
+    let mut wrong_id = proof.clone();
+    wrong_id.id = 7;
+    assert_eq!(
+        verify_draw_proof(&root, 100, &wrong_id),
+        Err(SortitionError::InvalidProof)
+    );
+
+    // Moving value between siblings keeps the sums but not the hashes.
+    let mut wrong_values = proof.clone();
+    let last = wrong_values.levels.last_mut().unwrap();
+    last.children[0].1 += 1;
+    last.children[1].1 -= 1;
+    assert_eq!(
+        verify_draw_proof(&root, 100, &wrong_values),
+        Err(SortitionError::InvalidProof)
+    );
+
+    let mut wrong_position = proof.clone();
+    let last = wrong_position.levels.last_mut().unwrap();
+    last.position = (last.position + 1) % last.children.len();
+    assert_eq!(
+        verify_draw_proof(&root, 100, &wrong_position),
+        Err(SortitionError::InvalidProof)
+    );
+
+    // A proof made before a change does not verify against the new root.
+    trees.set(&1, 1, 3).unwrap();
+    let new_root = trees.state_root(&1).unwrap();
+    assert_eq!(
+        verify_draw_proof(&new_root, 100, &proof),
+        Err(SortitionError::InvalidProof)
+    );
+
+    let mut empty: SortitionSumTrees = SortitionSumTrees::new();
+    empty.create_tree(1, 2).unwrap();
+    assert_eq!(empty.draw_with_proof(&1, 0), Err(SortitionError::EmptyTree));
+}
+
+proptest! {
+    #[test]
+    fn incremental_state_root_test(
+        k in 2..=5usize,
+        batches in prop::collection::vec(
+            prop::collection::vec((0..30u128, 0..50u128), 0..20),
+            1..5,
+        ),
+    ) {
+        let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+        trees.create_tree(1, k).unwrap();
+        let mut fresh = trees.clone();
+        for batch in batches {
+            for &(id, value) in &batch {
+                trees.set(&1, value, id).unwrap();
+                fresh.set(&1, value, id).unwrap();
+            }
+            // Rehashing only what changed gives the root of hashing it all.
+            let mut unhashed = fresh.clone();
+            prop_assert_eq!(trees.state_root(&1), unhashed.state_root(&1));
+        }
+    }
+}
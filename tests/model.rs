@@ -0,0 +1,143 @@
+//! Model-based tests: random sequences of operations are applied to both
+//! `SortitionSumTrees` and a naive weighted list, which must always agree.
+
+use proptest::prelude::*;
+use sortition_sum_tree::{SortitionError, SortitionSumTree, SortitionSumTrees};
+
+#[derive(Debug, Clone)]
+enum Op {
+    Set { id: u128, value: u128 },
+    StakeOf { id: u128 },
+    Draw { number: u128 },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        // Few IDs and small values, so that removals, vacancy reuse and
+        // relocations are frequent.
+        3 => (0..24u128, prop_oneof![Just(0u128), 1..20u128])
+            .prop_map(|(id, value)| Op::Set { id, value }),
+        1 => (0..24u128).prop_map(|id| Op::StakeOf { id }),
+        1 => any::<u128>().prop_map(|number| Op::Draw { number }),
+    ]
+}
+
+/// The reference: the non-zero stakes, in the order they were first set.
+#[derive(Default)]
+struct Model {
+    stakes: Vec<(u128, u128)>,
+}
+
+impl Model {
+    fn set(&mut self, id: u128, value: u128) {
+        match self.stakes.iter().position(|&(other, _)| other == id) {
+            Some(position) if value == 0 => {
+                self.stakes.remove(position);
+            }
+            Some(position) => self.stakes[position].1 = value,
+            None if value != 0 => self.stakes.push((id, value)),
+            None => {}
+        }
+    }
+
+    fn stake_of(&self, id: u128) -> u128 {
+        self.stakes
+            .iter()
+            .find(|&&(other, _)| other == id)
+            .map_or(0, |&(_, value)| value)
+    }
+
+    fn total(&self) -> u128 {
+        self.stakes.iter().map(|&(_, value)| value).sum()
+    }
+}
+
+/// The non-vacant leaves from left to right, which is the order `draw` walks
+/// them in. Index order differs once a leaf has been moved down a level.
+fn leaves_left_to_right(tree: &SortitionSumTree) -> Vec<(u128, u128)> {
+    fn visit(tree: &SortitionSumTree, index: usize, leaves: &mut Vec<(u128, u128)>) {
+        let first_child = tree.k() * index + 1;
+        if first_child < tree.nodes().len() {
+            for child in first_child..(first_child + tree.k()).min(tree.nodes().len()) {
+                visit(tree, child, leaves);
+            }
+        } else if let Some(&id) = tree.id_at(index) {
+            leaves.push((id, tree.nodes()[index]));
+        }
+    }
+    let mut leaves = Vec::new();
+    visit(tree, 0, &mut leaves);
+    leaves
+}
+
+/// Draws by walking a weighted list, as a linear scan would.
+fn naive_draw(stakes: &[(u128, u128)], number: u128) -> Option<u128> {
+    let total: u128 = stakes.iter().map(|&(_, value)| value).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut number = number % total;
+    for &(id, value) in stakes {
+        if number < value {
+            return Some(id);
+        }
+        number -= value;
+    }
+    unreachable!()
+}
+
+fn check(trees: &SortitionSumTrees, model: &Model, k: u128) -> Result<(), TestCaseError> {
+    prop_assert_eq!(trees.tree(&k).unwrap().total(), model.total());
+    prop_assert_eq!(trees.len(&k).unwrap(), model.stakes.len());
+    prop_assert_eq!(trees.validate(&k).unwrap(), vec![]);
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn model_test(k in 2..=9u128, ops in prop::collection::vec(op(), 0..150)) {
+        let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+        trees.create_tree(k, k as usize).unwrap();
+        let mut model = Model::default();
+        for op in ops {
+            match op {
+                Op::Set { id, value } => {
+                    trees.set(&k, value, id).unwrap();
+                    model.set(id, value);
+                    prop_assert_eq!(trees.stake_of(&k, &id), Ok(value));
+                    check(&trees, &model, k)?;
+                }
+                Op::StakeOf { id } => {
+                    prop_assert_eq!(trees.stake_of(&k, &id), Ok(model.stake_of(id)));
+                }
+                Op::Draw { number } => {
+                    // The tree does not keep its leaves in insertion order, so
+                    // the list is taken in the order the tree lays them out.
+                    let leaves = leaves_left_to_right(trees.tree(&k).unwrap());
+                    let mut sorted_leaves = leaves.clone();
+                    sorted_leaves.sort_unstable();
+                    let mut sorted_stakes = model.stakes.clone();
+                    sorted_stakes.sort_unstable();
+                    prop_assert_eq!(sorted_leaves, sorted_stakes);
+                    prop_assert_eq!(
+                        trees.draw(&k, number),
+                        naive_draw(&leaves, number).ok_or(SortitionError::EmptyTree)
+                    );
+                }
+            }
+        }
+
+        // Every ID is drawn by exactly as many numbers below the total as its stake.
+        let total = model.total();
+        let mut draws = std::collections::HashMap::new();
+        for number in 0..total {
+            *draws.entry(trees.draw(&k, number).unwrap()).or_insert(0u128) += 1;
+        }
+        for &(id, value) in &model.stakes {
+            prop_assert_eq!(draws.get(&id).copied(), Some(value));
+        }
+        prop_assert_eq!(draws.len(), model.stakes.len());
+    }
+}
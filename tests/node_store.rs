@@ -0,0 +1,89 @@
+use sortition_sum_tree::{NodeStore, SortitionError, SortitionSumTree, SortitionSumTrees};
+
+#[test]
+fn node_store_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    let mut tree: SortitionSumTree = SortitionSumTree::new(2).unwrap();
+    for (id, value) in [(1, 25), (2, 25), (3, 25), (4, 25), (3, 0), (5, 10), (1, 40)] {
+        trees.set(&1, value, id).unwrap();
+        tree.set(value, id).unwrap();
+    }
+    assert_eq!(&tree, trees.tree(&1).unwrap());
+    assert_eq!(NodeStore::total(&tree), 100);
+    assert_eq!(NodeStore::stake_of(&tree, &5), 10);
+    for number in 0..100 {
+        assert_eq!(NodeStore::draw(&tree, number), trees.draw(&1, number));
+    }
+    assert_eq!(
+        NodeStore::query_leaves(&tree, 1, 2),
+        trees.query_leaves(&1, 1, 2).unwrap()
+    );
+    assert_eq!(
+        SortitionSumTree::<u64, u64>::new(1),
+        Err(SortitionError::InvalidK)
+    );
+}
+
+#[cfg(feature = "near")]
+mod lookup_map_store_tests {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+    use sortition_sum_tree::{LookupMapStore, NodeStore, SortitionError, SortitionSumTree};
+
+    #[test]
+    fn lookup_map_store_test() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut store: LookupMapStore<u64, u128> = LookupMapStore::new(b"t".to_vec(), 3).unwrap();
+        let mut tree: SortitionSumTree<u64, u128> = SortitionSumTree::new(3).unwrap();
+        for id in 0..40 {
+            store.set(id as u128 + 1, id).unwrap();
+            tree.set(id as u128 + 1, id).unwrap();
+        }
+        for id in (0..40).step_by(3) {
+            store.set(0, id).unwrap();
+            tree.set(0, id).unwrap();
+        }
+        for id in 40..50 {
+            store.set(7, id).unwrap();
+            tree.set(7, id).unwrap();
+        }
+
+        assert_eq!(store.node_count(), tree.nodes().len());
+        for (index, &value) in tree.nodes().iter().enumerate() {
+            assert_eq!(store.node(index), value);
+            assert_eq!(store.id_of(index).as_ref(), tree.id_at(index));
+        }
+        assert_eq!(store.last_vacant(), tree.stack().last().copied());
+        assert_eq!(store.stake_of(&38), 39);
+        let total = store.total();
+        for number in (0..total).step_by(7) {
+            assert_eq!(store.draw(number), NodeStore::draw(&tree, number));
+        }
+        assert_eq!(
+            store.query_leaves(5, 10),
+            NodeStore::query_leaves(&tree, 5, 10)
+        );
+    }
+
+    #[test]
+    fn lookup_map_store_reload_test() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut store: LookupMapStore<u64, u128> = LookupMapStore::new(b"t".to_vec(), 2).unwrap();
+        for id in 1..=4 {
+            store.set(25, id).unwrap();
+        }
+        let bytes = borsh::BorshSerialize::try_to_vec(&store).unwrap();
+        let mut loaded: LookupMapStore<u64, u128> =
+            borsh::BorshDeserialize::try_from_slice(&bytes).unwrap();
+        assert_eq!(loaded.draw(80), Ok(2));
+        let index = store.index_of(&2);
+        loaded.set(0, 2).unwrap();
+        assert_eq!(loaded.total(), 75);
+        assert_eq!(loaded.last_vacant(), index);
+        assert!(matches!(
+            LookupMapStore::<u64, u128>::new(b"u".to_vec(), 1),
+            Err(SortitionError::InvalidK)
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use proptest::prelude::*;
+use sortition_sum_tree::{keccak256, SortitionError, SortitionObserver, SortitionSumTrees, U256};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    TreeCreated(u128, usize),
+    Inserted(u128, u128, u64, usize),
+    Updated(u128, u128, u64, u64, usize),
+    Removed(u128, u128, u64, usize),
+    Relocated(u128, u128, usize, usize),
+    Drawn(u128, u128),
+    Began,
+    Committed,
+    RolledBack,
+}
+
+#[derive(Debug, Default)]
+struct Recorder(RefCell<Vec<Event>>);
+
+impl Recorder {
+    fn take(&self) -> Vec<Event> {
+        self.0.take()
+    }
+}
+
+impl SortitionObserver<u128, u128, u64> for Recorder {
+    fn on_tree_created(&self, key: &u128, k: usize) {
+        self.0.borrow_mut().push(Event::TreeCreated(*key, k));
+    }
+
+    fn on_stake_inserted(&self, key: &u128, id: &u128, value: u64, tree_index: usize) {
+        self.0
+            .borrow_mut()
+            .push(Event::Inserted(*key, *id, value, tree_index));
+    }
+
+    fn on_stake_updated(
+        &self,
+        key: &u128,
+        id: &u128,
+        old_value: u64,
+        new_value: u64,
+        tree_index: usize,
+    ) {
+        self.0
+            .borrow_mut()
+            .push(Event::Updated(*key, *id, old_value, new_value, tree_index));
+    }
+
+    fn on_stake_removed(&self, key: &u128, id: &u128, old_value: u64, tree_index: usize) {
+        self.0
+            .borrow_mut()
+            .push(Event::Removed(*key, *id, old_value, tree_index));
+    }
+
+    fn on_leaf_relocated(&self, key: &u128, id: &u128, from_index: usize, to_index: usize) {
+        self.0
+            .borrow_mut()
+            .push(Event::Relocated(*key, *id, from_index, to_index));
+    }
+
+    fn on_draw(&self, key: &u128, id: &u128) {
+        self.0.borrow_mut().push(Event::Drawn(*key, *id));
+    }
+
+    fn on_begin(&self) {
+        self.0.borrow_mut().push(Event::Began);
+    }
+
+    fn on_commit(&self) {
+        self.0.borrow_mut().push(Event::Committed);
+    }
+
+    fn on_rollback(&self) {
+        self.0.borrow_mut().push(Event::RolledBack);
+    }
+}
+
+fn observed() -> SortitionSumTrees<u128, u128, u64, Recorder> {
+    SortitionSumTrees::new().with_observer(Recorder::default())
+}
+
+#[test]
+fn set_events_test() {
+    let mut trees = observed();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, 10, 1).unwrap();
+    trees.set(&1, 20, 2).unwrap();
+    // The third leaf is the first child of leaf 1, which moves down.
+    trees.set(&1, 30, 3).unwrap();
+    trees.set(&1, 25, 2).unwrap();
+    trees.set(&1, 25, 2).unwrap();
+    trees.set(&1, 0, 3).unwrap();
+    trees.set(&1, 0, 3).unwrap();
+    trees.set(&1, 40, 4).unwrap();
+    assert_eq!(
+        trees.observer().take(),
+        vec![
+            Event::TreeCreated(1, 2),
+            Event::Inserted(1, 1, 10, 1),
+            Event::Inserted(1, 2, 20, 2),
+            Event::Relocated(1, 1, 1, 4),
+            Event::Inserted(1, 3, 30, 3),
+            Event::Updated(1, 2, 20, 25, 2),
+            Event::Removed(1, 3, 30, 3),
+            Event::Inserted(1, 4, 40, 3),
+        ]
+    );
+
+    // Failed operations report nothing.
+    assert_eq!(
+        trees.create_tree(1, 2),
+        Err(SortitionError::TreeAlreadyExists)
+    );
+    assert_eq!(trees.set(&1, u64::MAX, 5), Err(SortitionError::Overflow));
+    assert_eq!(
+        trees.set_many(&1, [(1, 0), (5, u64::MAX)]),
+        Err(SortitionError::Overflow)
+    );
+    assert_eq!(
+        trees.transfer_stake(&1, 1, 5, 11),
+        Err(SortitionError::InsufficientStake)
+    );
+    assert_eq!(trees.observer().take(), vec![]);
+
+    trees.set_many(&1, [(1, 0), (2, 5), (5, 50)]).unwrap();
+    trees.transfer_stake(&1, 4, 2, 40).unwrap();
+    trees.increase_stake(&1, 1, 5).unwrap();
+    assert_eq!(
+        trees.observer().take(),
+        vec![
+            Event::Removed(1, 1, 10, 4),
+            Event::Updated(1, 2, 25, 5, 2),
+            Event::Inserted(1, 5, 50, 4),
+            Event::Removed(1, 4, 40, 3),
+            Event::Updated(1, 2, 5, 45, 2),
+            Event::Updated(1, 5, 50, 51, 4),
+        ]
+    );
+}
+
+#[test]
+fn draw_events_test() {
+    let mut trees = observed();
+    trees.create_tree(1, 3).unwrap();
+    for id in 1..=5 {
+        trees.set(&1, id as u64 * 10, id).unwrap();
+    }
+    trees.observer().take();
+
+    let id = trees.draw(&1, 35).unwrap();
+    let many = trees
+        .draw_many(&1, keccak256(b"seed"), U256::zero(), 3)
+        .unwrap();
+    let proof = trees.draw_with_proof(&1, 35).unwrap();
+    let mut expected = vec![Event::Drawn(1, id)];
+    expected.extend(many.iter().map(|&id| Event::Drawn(1, id)));
+    expected.push(Event::Drawn(1, proof.id));
+    assert_eq!(trees.observer().take(), expected);
+
+    // Distinct draws do not write to the tree, so only the draws are reported.
+    let distinct = trees
+        .draw_distinct(&1, keccak256(b"seed"), U256::zero(), 3)
+        .unwrap();
+    let expected: Vec<Event> = distinct.iter().map(|&id| Event::Drawn(1, id)).collect();
+    assert_eq!(trees.observer().take(), expected);
+
+    let mut empty = observed();
+    empty.create_tree(2, 2).unwrap();
+    empty.observer().take();
+    assert_eq!(empty.draw(&2, 0), Err(SortitionError::EmptyTree));
+    assert_eq!(empty.observer().take(), vec![]);
+}
+
+#[test]
+fn transaction_events_test() {
+    let mut trees = observed();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, 10, 1).unwrap();
+    trees.observer().take();
+
+    trees.begin().unwrap();
+    trees.set(&1, 20, 2).unwrap();
+    trees.create_tree(2, 2).unwrap();
+    trees.rollback().unwrap();
+    assert_eq!(
+        trees.observer().take(),
+        vec![
+            Event::Began,
+            Event::Inserted(1, 2, 20, 2),
+            Event::TreeCreated(2, 2),
+            Event::RolledBack,
+        ]
+    );
+
+    trees.transaction(|trees| trees.set(&1, 15, 1)).unwrap();
+    assert_eq!(
+        trees.observer().take(),
+        vec![
+            Event::Began,
+            Event::Updated(1, 1, 10, 15, 1),
+            Event::Committed,
+        ]
+    );
+}
+
+/// Follows the values and leaves of a tree from its events alone, going back
+/// to its state at `on_begin` on `on_rollback`.
+#[derive(Debug, Default)]
+struct Mirror {
+    values: RefCell<HashMap<u128, u64>>,
+    leaves: RefCell<HashMap<u128, usize>>,
+    saved: RefCell<Option<Saved>>,
+}
+
+/// The values and leaves of a [`Mirror`] when the transaction began.
+type Saved = (HashMap<u128, u64>, HashMap<u128, usize>);
+
+impl SortitionObserver<u128, u128, u64> for Mirror {
+    fn on_stake_inserted(&self, _key: &u128, id: &u128, value: u64, tree_index: usize) {
+        self.values.borrow_mut().insert(*id, value);
+        self.leaves.borrow_mut().insert(*id, tree_index);
+    }
+
+    fn on_stake_updated(&self, _key: &u128, id: &u128, old_value: u64, new_value: u64, _: usize) {
+        let previous = self.values.borrow_mut().insert(*id, new_value);
+        assert_eq!(previous, Some(old_value));
+    }
+
+    fn on_stake_removed(&self, _key: &u128, id: &u128, old_value: u64, _: usize) {
+        assert_eq!(self.values.borrow_mut().remove(id), Some(old_value));
+        self.leaves.borrow_mut().remove(id);
+    }
+
+    fn on_leaf_relocated(&self, _key: &u128, id: &u128, from_index: usize, to_index: usize) {
+        let previous = self.leaves.borrow_mut().insert(*id, to_index);
+        assert_eq!(previous, Some(from_index));
+    }
+
+    fn on_begin(&self) {
+        let saved = (self.values.borrow().clone(), self.leaves.borrow().clone());
+        *self.saved.borrow_mut() = Some(saved);
+    }
+
+    fn on_commit(&self) {
+        self.saved.take();
+    }
+
+    fn on_rollback(&self) {
+        let (values, leaves) = self.saved.take().expect("a transaction began");
+        *self.values.borrow_mut() = values;
+        *self.leaves.borrow_mut() = leaves;
+    }
+}
+
+proptest! {
+    #[test]
+    fn mirror_test(
+        k in 2..=5usize,
+        batches in prop::collection::vec(
+            prop::collection::vec((0..25u128, 0..40u64), 0..20),
+            1..6,
+        ),
+    ) {
+        let mut trees = SortitionSumTrees::new().with_observer(Mirror::default());
+        let mut unobserved: SortitionSumTrees<u128, u128, u64> = SortitionSumTrees::new();
+        trees.create_tree(1, k).unwrap();
+        unobserved.create_tree(1, k).unwrap();
+        for (i, batch) in batches.into_iter().enumerate() {
+            if i % 3 == 2 {
+                // Rolled back, so the mirror must drop these events.
+                trees.begin().unwrap();
+                trees.set_many(&1, batch).unwrap();
+                trees.rollback().unwrap();
+            } else if i % 2 == 0 {
+                for &(id, value) in &batch {
+                    trees.set(&1, value, id).unwrap();
+                    unobserved.set(&1, value, id).unwrap();
+                }
+            } else {
+                trees.set_many(&1, batch.clone()).unwrap();
+                unobserved.set_many(&1, batch).unwrap();
+            }
+        }
+        prop_assert_eq!(&trees, &unobserved.with_observer(Mirror::default()));
+
+        let tree = trees.tree(&1).unwrap();
+        let values: HashMap<u128, u64> = tree.iter().map(|(&id, value)| (id, value)).collect();
+        let leaves: HashMap<u128, usize> = tree
+            .iter()
+            .map(|(&id, _)| (id, tree.node_index_of(&id).unwrap()))
+            .collect();
+        prop_assert_eq!(&*trees.observer().values.borrow(), &values);
+        prop_assert_eq!(&*trees.observer().leaves.borrow(), &leaves);
+    }
+}
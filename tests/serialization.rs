@@ -0,0 +1,146 @@
+#![cfg(any(feature = "serde", feature = "borsh"))]
+
+use sortition_sum_tree::SortitionSumTrees;
+
+fn trees() -> SortitionSumTrees<u64, u64, u128> {
+    let mut trees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.create_tree(2, 3).unwrap();
+    for id in 1..=9 {
+        trees.set(&1, id as u128 * 10, id).unwrap();
+        trees.set(&2, id as u128, id).unwrap();
+    }
+    trees.set(&1, 0, 4).unwrap();
+    trees.set(&1, 0, 7).unwrap();
+    trees
+}
+
+#[cfg(feature = "serde")]
+mod serde_tests {
+    use serde_json::{json, Value};
+    use sortition_sum_tree::{SortitionSumTree, SortitionSumTrees, U256};
+
+    use super::trees;
+
+    fn load(value: Value) -> Result<SortitionSumTree<u64, u128>, String> {
+        serde_json::from_value(value).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn serde_round_trip_test() {
+        let trees = trees();
+        let json = serde_json::to_string(&trees).unwrap();
+        let loaded: SortitionSumTrees<u64, u64, u128> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, trees);
+        assert_eq!(loaded.draw(&1, 123), trees.draw(&1, 123));
+
+        let mut wide: SortitionSumTrees<String, String, U256> = SortitionSumTrees::new();
+        wide.create_tree("court".to_string(), 4).unwrap();
+        wide.set(&"court".to_string(), U256::MAX, "alice".to_string())
+            .unwrap();
+        let json = serde_json::to_string(&wide).unwrap();
+        assert_eq!(
+            serde_json::from_str::<SortitionSumTrees<String, String, U256>>(&json).unwrap(),
+            wide
+        );
+    }
+
+    #[test]
+    fn serde_format_test() {
+        let mut trees: SortitionSumTrees<u64, u64, u128> = SortitionSumTrees::new();
+        trees.create_tree(1, 2).unwrap();
+        trees.set(&1, 5, 10).unwrap();
+        trees.set(&1, 6, 20).unwrap();
+        trees.set(&1, 7, 30).unwrap();
+        trees.set(&1, 0, 20).unwrap();
+        assert_eq!(
+            serde_json::to_value(trees.tree(&1).unwrap()).unwrap(),
+            json!({"V1": {
+                "k": 2,
+                "nodes": [12, 12, 0, 7, 5],
+                "stack": [2],
+                "ids": [[3, 30], [4, 10]],
+            }})
+        );
+    }
+
+    #[test]
+    fn serde_huge_k_test() {
+        let trees: SortitionSumTrees<u64, u64, u128> = serde_json::from_value(
+            json!({"1": {"V1": {"k": u64::MAX, "nodes": [3, 1, 2], "stack": [], "ids": [[1, 10], [2, 20]]}}}),
+        )
+        .unwrap();
+        assert_eq!(trees.draw(&1, 2), Ok(20));
+        assert!(
+            load(json!({"V1": {"k": 2, "nodes": [5], "stack": [], "ids": []}}))
+                .unwrap_err()
+                .contains("node 0 is not the sum of its children")
+        );
+    }
+
+    #[test]
+    fn serde_rejects_corrupted_snapshots_test() {
+        let valid = json!({"V1": {"k": 2, "nodes": [18, 12, 6, 7, 5], "stack": [], "ids": [[2, 20], [3, 30], [4, 10]]}});
+        assert!(load(valid.clone()).is_ok());
+        let corrupt = |path: &str, value: Value| {
+            let mut snapshot = valid.clone();
+            *snapshot.pointer_mut(path).unwrap() = value;
+            load(snapshot).unwrap_err()
+        };
+        assert!(corrupt("/V1/nodes/3", json!(8)).contains("node 1 is not the sum of its children"));
+        assert!(corrupt("/V1/k", json!(1)).contains("K must be at least 2"));
+        assert!(corrupt("/V1/stack", json!([1])).contains("stacked node 1 is not a leaf"));
+        assert!(corrupt("/V1/stack", json!([2])).contains("stacked node 2 is not vacant"));
+        assert!(corrupt("/V1/ids/0", json!([1, 20])).contains("labelled node 1 is not a leaf"));
+        assert!(corrupt("/V1/ids/0", json!([2, 30])).contains("labels several nodes"));
+        assert!(corrupt("/V1/ids/0", json!([3, 20])).contains("node 3 has several IDs"));
+        assert!(corrupt("/V1/ids", json!([[3, 30], [4, 10]]))
+            .contains("leaf 2 is neither labelled nor stacked"));
+        assert!(corrupt("/V1/nodes", json!([])).contains("no root node"));
+
+        let unknown_version = json!({"V2": {"k": 2, "nodes": [0], "stack": [], "ids": []}});
+        assert!(load(unknown_version)
+            .unwrap_err()
+            .contains("unknown variant `V2`"));
+    }
+}
+
+#[cfg(feature = "borsh")]
+mod borsh_tests {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use sortition_sum_tree::{SortitionSumTree, SortitionSumTrees};
+
+    use super::trees;
+
+    #[test]
+    fn borsh_round_trip_test() {
+        let trees = trees();
+        let bytes = trees.try_to_vec().unwrap();
+        let loaded = SortitionSumTrees::<u64, u64, u128>::try_from_slice(&bytes).unwrap();
+        assert_eq!(loaded, trees);
+        // Trees and labels are written in order, so the bytes are deterministic.
+        assert_eq!(loaded.try_to_vec().unwrap(), bytes);
+    }
+
+    #[test]
+    fn borsh_rejects_corrupted_snapshots_test() {
+        let mut trees: SortitionSumTrees<u64, u64, u64> = SortitionSumTrees::new();
+        trees.create_tree(1, 2).unwrap();
+        trees.set(&1, 5, 10).unwrap();
+        let mut bytes = trees.tree(&1).unwrap().try_to_vec().unwrap();
+        // Version tag, K, then the node count and the root.
+        assert_eq!(bytes[0], 0);
+        assert_eq!(bytes[1..9], 2u64.to_le_bytes());
+        assert_eq!(bytes[13..21], 5u64.to_le_bytes());
+        bytes[13] = 6;
+        let error = SortitionSumTree::<u64, u64>::try_from_slice(&bytes).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error
+            .to_string()
+            .contains("node 0 is not the sum of its children"));
+
+        bytes[13] = 5;
+        bytes[0] = 1;
+        assert!(SortitionSumTree::<u64, u64>::try_from_slice(&bytes).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use proptest::prelude::*;
+use sortition_sum_tree::{SortitionError, SortitionSumTrees};
+
+/// Applies the entries one `set` at a time.
+fn set_each(trees: &mut SortitionSumTrees, entries: &[(u128, u128)]) {
+    for &(id, value) in entries {
+        trees.set(&1, value, id).unwrap();
+    }
+}
+
+#[test]
+fn set_many_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 3).unwrap();
+    set_each(&mut trees, &[(1, 10), (2, 20), (3, 30), (4, 40)]);
+    let mut expected = trees.clone();
+
+    // Updates, removals, inserts into vacant leaves and appends that move a
+    // leaf down, with an ID repeated.
+    let entries = [
+        (2, 0),
+        (5, 50),
+        (1, 15),
+        (6, 60),
+        (7, 70),
+        (3, 0),
+        (8, 80),
+        (5, 55),
+        (9, 90),
+    ];
+    trees.set_many(&1, entries).unwrap();
+    set_each(&mut expected, &entries);
+    assert_eq!(trees, expected);
+    assert_eq!(trees.stake_of(&1, &5), Ok(55));
+    assert_eq!(trees.stake_of(&1, &2), Ok(0));
+    assert_eq!(
+        trees.tree(&1).unwrap().total(),
+        15 + 40 + 55 + 60 + 70 + 80 + 90
+    );
+    assert_eq!(trees.validate(&1), Ok(vec![]));
+
+    trees.set_many(&1, []).unwrap();
+    assert_eq!(trees, expected);
+}
+
+#[test]
+fn set_many_errors_test() {
+    let mut trees: SortitionSumTrees<u128, u128, u64> = SortitionSumTrees::new();
+    assert_eq!(
+        trees.set_many(&1, [(1, 1)]),
+        Err(SortitionError::TreeNotFound)
+    );
+    trees.create_tree(1, 2).unwrap();
+    trees.set_many(&1, [(1, u64::MAX - 10), (2, 5)]).unwrap();
+    let before = trees.clone();
+
+    // The second entry overflows, after the first was valid on its own.
+    assert_eq!(
+        trees.set_many(&1, [(3, 5), (4, 1)]),
+        Err(SortitionError::Overflow)
+    );
+    assert_eq!(
+        trees, before,
+        "a failed batch must leave the tree untouched"
+    );
+    // Only the last value of an ID counts.
+    assert_eq!(
+        trees.set_many(&1, [(3, 100), (3, 6)]),
+        Err(SortitionError::Overflow)
+    );
+    assert_eq!(trees, before);
+
+    // A decrease later in the batch makes room for an increase before it.
+    trees.set_many(&1, [(3, 10), (1, u64::MAX - 20)]).unwrap();
+    assert_eq!(trees.tree(&1).unwrap().total(), u64::MAX - 5);
+    assert_eq!(trees.validate(&1), Ok(vec![]));
+}
+
+proptest! {
+    #[test]
+    fn set_many_matches_set_test(
+        k in 2..=6usize,
+        initial in prop::collection::vec((0..40u128, 0..100u128), 0..40),
+        entries in prop::collection::vec((0..40u128, 0..100u128), 0..60),
+    ) {
+        let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+        trees.create_tree(1, k).unwrap();
+        set_each(&mut trees, &initial);
+        let mut expected = trees.clone();
+
+        trees.set_many(&1, entries.iter().copied()).unwrap();
+        set_each(&mut expected, &entries);
+        prop_assert_eq!(&trees, &expected);
+    }
+}
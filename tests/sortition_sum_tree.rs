@@ -0,0 +1,193 @@
+use sortition_sum_tree::{SortitionError, SortitionSumTrees, U256};
+
+#[test]
+fn build_tree_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, 25, 1).unwrap();
+    trees.set(&1, 25, 2).unwrap();
+    trees.set(&1, 25, 3).unwrap();
+    trees.set(&1, 25, 4).unwrap();
+    let tree = trees.tree(&1).unwrap();
+    assert_eq!(tree.nodes()[0], 100);
+    assert_eq!(tree.nodes()[1], 50);
+    assert_eq!(tree.nodes()[2], 50);
+    assert_eq!(tree.nodes()[3], 25);
+    assert_eq!(tree.nodes()[4], 25);
+    assert_eq!(tree.nodes()[5], 25);
+    assert_eq!(tree.nodes()[6], 25);
+    assert_eq!(tree.total(), 100);
+}
+#[test]
+fn remove_and_add_node_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, 25, 1).unwrap();
+    trees.set(&1, 25, 2).unwrap();
+    trees.set(&1, 25, 3).unwrap();
+    trees.set(&1, 25, 4).unwrap();
+    let index = trees.tree(&1).unwrap().node_index_of(&3).unwrap();
+    trees.set(&1, 0, 3).unwrap();
+    assert_eq!(trees.stake_of(&1, &3), Ok(0));
+    assert_eq!(trees.tree(&1).unwrap().stack()[0], index);
+    assert_eq!(trees.tree(&1).unwrap().id_at(index), None);
+    trees.set(&1, 25, 5).unwrap();
+    assert!(
+        trees.tree(&1).unwrap().stack().is_empty(),
+        "Error, stack is not empty!"
+    );
+    assert_eq!(trees.tree(&1).unwrap().node_index_of(&5), Some(index));
+}
+#[test]
+fn draw_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, 25, 1).unwrap();
+    trees.set(&1, 25, 2).unwrap();
+    trees.set(&1, 25, 3).unwrap();
+    trees.set(&1, 25, 4).unwrap();
+    let addr = trees.draw(&1, 20).unwrap();
+    assert_eq!(3, addr);
+    let addr = trees.draw(&1, 40).unwrap();
+    assert_eq!(1, addr);
+    let addr = trees.draw(&1, 60).unwrap();
+    assert_eq!(4, addr);
+    let addr = trees.draw(&1, 80).unwrap();
+    assert_eq!(2, addr);
+}
+#[test]
+fn errors_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    assert_eq!(trees.create_tree(1, 1), Err(SortitionError::InvalidK));
+    assert_eq!(trees.set(&1, 25, 1), Err(SortitionError::TreeNotFound));
+    assert_eq!(trees.stake_of(&1, &1), Err(SortitionError::TreeNotFound));
+    assert_eq!(trees.draw(&1, 0), Err(SortitionError::TreeNotFound));
+    assert_eq!(
+        trees.query_leaves(&1, 0, 1),
+        Err(SortitionError::TreeNotFound)
+    );
+    trees.create_tree(1, 2).unwrap();
+    assert_eq!(
+        trees.create_tree(1, 2),
+        Err(SortitionError::TreeAlreadyExists)
+    );
+    assert_eq!(trees.draw(&1, 0), Err(SortitionError::EmptyTree));
+    trees.set(&1, u128::MAX, 1).unwrap();
+    assert_eq!(trees.set(&1, 1, 2), Err(SortitionError::Overflow));
+}
+#[test]
+fn generic_types_test() {
+    let mut trees: SortitionSumTrees<String, String, u64> = SortitionSumTrees::new();
+    let court = "general".to_string();
+    trees.create_tree(court.clone(), 3).unwrap();
+    trees.set(&court, 10, "alice.near".to_string()).unwrap();
+    trees.set(&court, 30, "bob.near".to_string()).unwrap();
+    assert_eq!(trees.stake_of(&court, &"bob.near".to_string()), Ok(30));
+    assert_eq!(trees.draw(&court, 5).unwrap(), "alice.near");
+    assert_eq!(trees.draw(&court, 15).unwrap(), "bob.near");
+
+    let mut trees: SortitionSumTrees<[u8; 32], u64, U256> = SortitionSumTrees::new();
+    trees.create_tree([7; 32], 2).unwrap();
+    trees.set(&[7; 32], U256::MAX - 1, 1).unwrap();
+    trees.set(&[7; 32], U256::one(), 2).unwrap();
+    assert_eq!(trees.tree(&[7; 32]).unwrap().total(), U256::MAX);
+    assert_eq!(trees.draw(&[7; 32], U256::MAX - 1), Ok(2));
+    assert_eq!(
+        trees.set(&[7; 32], U256::one(), 3),
+        Err(SortitionError::Overflow)
+    );
+}
+#[test]
+fn overflow_leaves_tree_untouched_test() {
+    let mut trees: SortitionSumTrees<u128, u128, u64> = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, u64::MAX - 10, 1).unwrap();
+    trees.set(&1, 5, 2).unwrap();
+    let before = trees.clone();
+
+    // Update of an existing leaf.
+    assert_eq!(trees.set(&1, 16, 2), Err(SortitionError::Overflow));
+    assert_eq!(trees, before);
+    // Append of a first child, which would also move its parent leaf down.
+    assert_eq!(trees.set(&1, 6, 3), Err(SortitionError::Overflow));
+    assert_eq!(trees, before);
+
+    // Reuse of a vacant leaf.
+    trees.set(&1, 0, 2).unwrap();
+    let before = trees.clone();
+    assert_eq!(trees.set(&1, 11, 4), Err(SortitionError::Overflow));
+    assert_eq!(trees, before);
+
+    trees.set(&1, 10, 4).unwrap();
+    assert_eq!(trees.tree(&1).unwrap().total(), u64::MAX);
+}
+#[test]
+fn query_leaves_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    let page = trees.query_leaves(&1, 0, 10).unwrap();
+    assert_eq!(page.start_index, 0);
+    assert!(page.entries.is_empty());
+    assert_eq!(page.next_cursor, None);
+
+    trees.set(&1, 25, 1).unwrap();
+    trees.set(&1, 26, 2).unwrap();
+    trees.set(&1, 27, 3).unwrap();
+    trees.set(&1, 28, 4).unwrap();
+    let page = trees.query_leaves(&1, 0, 10).unwrap();
+    assert_eq!(page.start_index, 3);
+    assert_eq!(page.entries, vec![(3, 27), (1, 25), (4, 28), (2, 26)]);
+    assert_eq!(page.next_cursor, None);
+
+    let page = trees.query_leaves(&1, 0, 2).unwrap();
+    assert_eq!(page.entries, vec![(3, 27), (1, 25)]);
+    assert_eq!(page.next_cursor, Some(2));
+    let page = trees.query_leaves(&1, 2, 2).unwrap();
+    assert_eq!(page.entries, vec![(4, 28), (2, 26)]);
+    assert_eq!(page.next_cursor, None);
+
+    // Vacant leaves are skipped and do not count towards `count`.
+    trees.set(&1, 0, 1).unwrap();
+    let page = trees.query_leaves(&1, 0, 2).unwrap();
+    assert_eq!(page.entries, vec![(3, 27), (4, 28)]);
+    assert_eq!(page.next_cursor, Some(3));
+    let page = trees.query_leaves(&1, 3, 2).unwrap();
+    assert_eq!(page.entries, vec![(2, 26)]);
+    assert_eq!(page.next_cursor, None);
+    assert!(trees.query_leaves(&1, 100, 2).unwrap().entries.is_empty());
+}
+#[test]
+fn iter_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    assert_eq!(trees.len(&1), Err(SortitionError::TreeNotFound));
+    assert!(trees.iter(&1).is_err());
+    trees.create_tree(1, 3).unwrap();
+    assert_eq!(trees.len(&1), Ok(0));
+    assert_eq!(trees.is_empty(&1), Ok(true));
+    assert_eq!(trees.iter(&1).unwrap().count(), 0);
+
+    for id in 1..=7 {
+        trees.set(&1, id * 10, id).unwrap();
+    }
+    trees.set(&1, 0, 3).unwrap();
+    trees.set(&1, 0, 6).unwrap();
+    assert_eq!(trees.len(&1), Ok(5));
+    assert_eq!(trees.is_empty(&1), Ok(false));
+
+    let entries: Vec<(u128, u128)> = trees
+        .iter(&1)
+        .unwrap()
+        .map(|(id, value)| (*id, value))
+        .collect();
+    let page = trees.query_leaves(&1, 0, usize::MAX).unwrap();
+    assert_eq!(entries, page.entries);
+    assert_eq!(entries.len(), 5);
+    for &(id, value) in &entries {
+        assert_eq!(trees.stake_of(&1, &id), Ok(value));
+    }
+    let total: u128 = entries.iter().map(|(_, value)| value).sum();
+    assert_eq!(total, trees.tree(&1).unwrap().total());
+
+    let ids: Vec<u128> = trees.ids(&1).unwrap().copied().collect();
+    assert_eq!(ids, entries.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+}
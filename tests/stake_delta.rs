@@ -0,0 +1,94 @@
+use sortition_sum_tree::{SortitionError, SortitionSumTrees};
+
+fn trees() -> SortitionSumTrees<u128, u128, u64> {
+    let mut trees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    for id in 1..=3 {
+        trees.set(&1, id as u64 * 10, id).unwrap();
+    }
+    trees
+}
+
+#[test]
+fn increase_and_decrease_stake_test() {
+    let mut trees = trees();
+    trees.increase_stake(&1, 5, 2).unwrap();
+    assert_eq!(trees.stake_of(&1, &2), Ok(25));
+    trees.increase_stake(&1, 7, 4).unwrap();
+    assert_eq!(trees.stake_of(&1, &4), Ok(7));
+    trees.decrease_stake(&1, 5, 3).unwrap();
+    assert_eq!(trees.stake_of(&1, &3), Ok(25));
+    assert_eq!(trees.tree(&1).unwrap().total(), 10 + 25 + 25 + 7);
+
+    // Decreasing to 0 removes the ID, like `set` does.
+    trees.decrease_stake(&1, 10, 1).unwrap();
+    let mut expected = trees.clone();
+    expected.set(&1, 0, 1).unwrap();
+    assert_eq!(trees, expected);
+    assert_eq!(trees.tree(&1).unwrap().node_index_of(&1), None);
+    assert_eq!(trees.validate(&1), Ok(vec![]));
+}
+
+#[test]
+fn stake_delta_errors_test() {
+    let mut trees = trees();
+    let before = trees.clone();
+    assert_eq!(
+        trees.decrease_stake(&1, 11, 1),
+        Err(SortitionError::InsufficientStake)
+    );
+    assert_eq!(
+        trees.decrease_stake(&1, 1, 9),
+        Err(SortitionError::InsufficientStake)
+    );
+    assert_eq!(
+        trees.increase_stake(&1, u64::MAX - 10, 1),
+        Err(SortitionError::Overflow)
+    );
+    assert_eq!(
+        trees.increase_stake(&1, u64::MAX, 1),
+        Err(SortitionError::Overflow)
+    );
+    assert_eq!(trees, before);
+    assert_eq!(
+        trees.increase_stake(&2, 1, 1),
+        Err(SortitionError::TreeNotFound)
+    );
+}
+
+#[test]
+fn transfer_stake_test() {
+    let mut trees = trees();
+    let index = trees.tree(&1).unwrap().node_index_of(&3);
+    trees.transfer_stake(&1, 3, 1, 12).unwrap();
+    assert_eq!(trees.stake_of(&1, &3), Ok(18));
+    assert_eq!(trees.stake_of(&1, &1), Ok(22));
+    trees.transfer_stake(&1, 3, 4, 18).unwrap();
+    assert_eq!(trees.stake_of(&1, &3), Ok(0));
+    assert_eq!(trees.stake_of(&1, &4), Ok(18));
+    // The new ID reuses the leaf the other one vacated.
+    assert_eq!(trees.tree(&1).unwrap().node_index_of(&4), index);
+    assert_eq!(trees.tree(&1).unwrap().total(), 60);
+    assert_eq!(trees.validate(&1), Ok(vec![]));
+
+    let before = trees.clone();
+    trees.transfer_stake(&1, 2, 2, 20).unwrap();
+    assert_eq!(trees, before);
+    assert_eq!(
+        trees.transfer_stake(&1, 2, 1, 21),
+        Err(SortitionError::InsufficientStake)
+    );
+    assert_eq!(
+        trees.transfer_stake(&1, 2, 2, 21),
+        Err(SortitionError::InsufficientStake)
+    );
+    assert_eq!(trees, before);
+
+    // A transfer to a new ID lays the tree out like the two sets it stands for.
+    let mut sets = trees.clone();
+    sets.set(&1, 10, 2).unwrap();
+    sets.set(&1, 10, 5).unwrap();
+    trees.transfer_stake(&1, 2, 5, 10).unwrap();
+    assert_eq!(trees, sets);
+    assert_eq!(trees.validate(&1), Ok(vec![]));
+}
@@ -0,0 +1,99 @@
+use proptest::prelude::*;
+use sortition_sum_tree::{verify_stake_proof, SortitionError, SortitionSumTrees};
+
+#[test]
+fn prove_stake_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, 10, 1).unwrap();
+    trees.set(&1, 20, 2).unwrap();
+    let root = trees.state_root(&1).unwrap();
+    let proof = trees.prove_stake(&1, &1).unwrap();
+    assert_eq!(verify_stake_proof(&root, &1u128, 10, &proof), Ok(()));
+
+    // Appending a third ID moves the leaf of ID 1 down.
+    let index = trees.tree(&1).unwrap().node_index_of(&1);
+    trees.set(&1, 30, 3).unwrap();
+    assert_ne!(trees.tree(&1).unwrap().node_index_of(&1), index);
+    let new_root = trees.state_root(&1).unwrap();
+    let moved = trees.prove_stake(&1, &1).unwrap();
+    assert_eq!(moved.levels.len(), 2);
+    assert_eq!(verify_stake_proof(&new_root, &1u128, 10, &moved), Ok(()));
+    assert_eq!(
+        verify_stake_proof(&new_root, &1u128, 10, &proof),
+        Err(SortitionError::InvalidProof)
+    );
+    for id in 2..=3 {
+        let proof = trees.prove_stake(&1, &id).unwrap();
+        assert_eq!(verify_stake_proof(&new_root, &id, id * 10, &proof), Ok(()));
+    }
+}
+
+#[test]
+fn verify_stake_proof_errors_test() {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 3).unwrap();
+    for id in 1..=6 {
+        trees.set(&1, id * 10, id).unwrap();
+    }
+    let root = trees.state_root(&1).unwrap();
+    let proof = trees.prove_stake(&1, &4).unwrap();
+    assert_eq!(verify_stake_proof(&root, &4u128, 40, &proof), Ok(()));
+
+    assert_eq!(
+        verify_stake_proof(&root, &4u128, 41, &proof),
+        Err(SortitionError::InvalidProof)
+    );
+    assert_eq!(
+        verify_stake_proof(&root, &5u128, 40, &proof),
+        Err(SortitionError::InvalidProof)
+    );
+    let mut wrong_root = root;
+    wrong_root[31] ^= 1;
+    assert_eq!(
+        verify_stake_proof(&wrong_root, &4u128, 40, &proof),
+        Err(SortitionError::InvalidProof)
+    );
+    // A value moved between siblings changes the hashes, but not the sums.
+    let mut wrong_values = proof.clone();
+    let top = &mut wrong_values.levels[0];
+    let other = (top.position + 1) % top.children.len();
+    top.children[top.position].1 += 1;
+    top.children[other].1 -= 1;
+    assert_eq!(
+        verify_stake_proof(&root, &4u128, 40, &wrong_values),
+        Err(SortitionError::InvalidProof)
+    );
+    let mut no_levels = proof.clone();
+    no_levels.levels.clear();
+    assert_eq!(
+        verify_stake_proof(&root, &4u128, 40, &no_levels),
+        Err(SortitionError::InvalidProof)
+    );
+
+    assert_eq!(trees.prove_stake(&1, &7), Err(SortitionError::IdNotFound));
+    trees.set(&1, 0, 4).unwrap();
+    assert_eq!(trees.prove_stake(&1, &4), Err(SortitionError::IdNotFound));
+    assert_eq!(trees.prove_stake(&2, &4), Err(SortitionError::TreeNotFound));
+}
+
+proptest! {
+    #[test]
+    fn prove_every_stake_test(
+        k in 2..=5usize,
+        sets in prop::collection::vec((0..30u128, 0..50u128), 0..60),
+    ) {
+        let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+        trees.create_tree(1, k).unwrap();
+        for (id, value) in sets {
+            trees.set(&1, value, id).unwrap();
+        }
+        let root = trees.state_root(&1).unwrap();
+        let stakes: Vec<(u128, u128)> =
+            trees.iter(&1).unwrap().map(|(&id, value)| (id, value)).collect();
+        for (id, value) in stakes {
+            let proof = trees.prove_stake(&1, &id).unwrap();
+            prop_assert_eq!(verify_stake_proof(&root, &id, value, &proof), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use proptest::prelude::*;
+use sortition_sum_tree::{keccak256, SnapshotId, SortitionError, SortitionSumTrees, U256};
+
+fn trees() -> SortitionSumTrees {
+    let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+    trees.create_tree(1, 3).unwrap();
+    trees.create_tree(2, 2).unwrap();
+    for id in 1..=6 {
+        trees.set(&1, id * 10, id).unwrap();
+        trees.set(&2, id, id).unwrap();
+    }
+    trees.set(&1, 0, 2).unwrap();
+    trees
+}
+
+#[test]
+fn rollback_test() {
+    let mut trees = trees();
+    let before = trees.clone();
+
+    trees.begin().unwrap();
+    trees.set(&1, 0, 4).unwrap();
+    trees.set(&1, 70, 7).unwrap();
+    trees.set(&1, 80, 8).unwrap();
+    trees
+        .set_many(&2, [(1, 0), (9, 90), (10, 100), (3, 33)])
+        .unwrap();
+    trees.transfer_stake(&1, 5, 6, 25).unwrap();
+    trees
+        .draw_distinct(&1, keccak256(b"seed"), U256::zero(), 3)
+        .unwrap();
+    trees.create_tree(3, 4).unwrap();
+    trees.set(&3, 1, 1).unwrap();
+    trees.rollback().unwrap();
+    assert_eq!(trees, before);
+
+    // The stacks are restored too, so insertions land in the same leaves.
+    let mut expected = before.clone();
+    for trees in [&mut trees, &mut expected] {
+        trees.set(&1, 11, 11).unwrap();
+        trees.set(&1, 12, 12).unwrap();
+    }
+    assert_eq!(trees, expected);
+    assert_eq!(trees.validate(&1), Ok(vec![]));
+}
+
+#[test]
+fn commit_test() {
+    let mut trees = trees();
+    let mut expected = trees.clone();
+    trees.begin().unwrap();
+    trees.set(&1, 0, 4).unwrap();
+    trees.create_tree(3, 4).unwrap();
+    trees.commit().unwrap();
+    expected.set(&1, 0, 4).unwrap();
+    expected.create_tree(3, 4).unwrap();
+    assert_eq!(trees, expected);
+
+    // A committed transaction is no longer undone.
+    trees.begin().unwrap();
+    trees.set(&1, 1, 1).unwrap();
+    trees.rollback().unwrap();
+    assert_eq!(trees, expected);
+}
+
+#[test]
+fn transaction_test() {
+    let mut trees: SortitionSumTrees<u128, u128, u64> = SortitionSumTrees::new();
+    trees.create_tree(1, 2).unwrap();
+    trees.set(&1, u64::MAX - 100, 1).unwrap();
+    let before = trees.clone();
+
+    let result = trees.transaction(|trees| {
+        trees.set(&1, 50, 2)?;
+        trees.set(&1, 40, 3)?;
+        trees.set(&1, 20, 4)
+    });
+    assert_eq!(result, Err(SortitionError::Overflow));
+    assert_eq!(trees, before);
+
+    let total = trees.transaction(|trees| {
+        trees.set(&1, 50, 2)?;
+        trees.set(&1, 40, 3)?;
+        Ok::<_, SortitionError>(trees.tree(&1)?.total())
+    });
+    assert_eq!(total, Ok(u64::MAX - 10));
+    assert_eq!(trees.stake_of(&1, &3), Ok(40));
+
+    // The closure may fail with its own error type.
+    #[derive(Debug, PartialEq)]
+    enum CallError {
+        Sortition(SortitionError),
+        Rejected,
+    }
+    impl From<SortitionError> for CallError {
+        fn from(error: SortitionError) -> Self {
+            CallError::Sortition(error)
+        }
+    }
+    let before = trees.clone();
+    let result: Result<(), CallError> = trees.transaction(|trees| {
+        trees.set(&1, 0, 2)?;
+        Err(CallError::Rejected)
+    });
+    assert_eq!(result, Err(CallError::Rejected));
+    assert_eq!(trees, before);
+}
+
+#[test]
+fn transaction_errors_test() {
+    let mut trees = trees();
+    assert_eq!(trees.commit(), Err(SortitionError::NoTransaction));
+    assert_eq!(trees.rollback(), Err(SortitionError::NoTransaction));
+    trees.begin().unwrap();
+    assert_eq!(trees.begin(), Err(SortitionError::TransactionInProgress));
+    assert_eq!(
+        trees.transaction(|_| Ok::<_, SortitionError>(())),
+        Err(SortitionError::TransactionInProgress)
+    );
+    trees.commit().unwrap();
+    assert_eq!(trees.commit(), Err(SortitionError::NoTransaction));
+}
+
+#[test]
+fn rollback_checkpoints_and_hashes_test() {
+    let mut trees = trees();
+    let snapshot = trees.checkpoint(&1).unwrap();
+    let root = trees.state_root(&1).unwrap();
+    let before = trees.clone();
+
+    trees.begin().unwrap();
+    trees.set(&1, 0, 1).unwrap();
+    trees.set(&1, 15, 7).unwrap();
+    trees.set(&1, 16, 8).unwrap();
+    assert_ne!(trees.state_root(&1).unwrap(), root);
+    assert_eq!(trees.checkpoint(&1), Ok(SnapshotId(1)));
+    trees.set(&1, 17, 9).unwrap();
+    trees.rollback().unwrap();
+
+    assert_eq!(trees, before);
+    assert_eq!(trees.state_root(&1), Ok(root));
+    assert_eq!(
+        trees.stake_of_at(&1, SnapshotId(1), &7),
+        Err(SortitionError::SnapshotNotFound)
+    );
+    assert_eq!(trees.stake_of_at(&1, snapshot, &1), Ok(10));
+    assert_eq!(trees.checkpoint(&1), Ok(SnapshotId(1)));
+
+    // A first checkpoint taken in the transaction goes away with it.
+    let mut trees = self::trees();
+    let before = trees.clone();
+    trees.begin().unwrap();
+    assert_eq!(trees.checkpoint(&1), Ok(SnapshotId(0)));
+    trees.set(&1, 15, 7).unwrap();
+    trees.rollback().unwrap();
+
+    assert_eq!(trees, before);
+    assert_eq!(
+        trees.stake_of_at(&1, SnapshotId(0), &1),
+        Err(SortitionError::SnapshotNotFound)
+    );
+    assert_eq!(trees.checkpoint(&1), Ok(SnapshotId(0)));
+}
+
+proptest! {
+    #[test]
+    fn rollback_restores_test(
+        k in 2..=5usize,
+        initial in prop::collection::vec((0..30u128, 0..50u128), 0..40),
+        changes in prop::collection::vec((0..30u128, 0..50u128), 0..40),
+        checkpointed in any::<bool>(),
+    ) {
+        let mut trees: SortitionSumTrees = SortitionSumTrees::new();
+        trees.create_tree(1, k).unwrap();
+        for (id, value) in initial {
+            trees.set(&1, value, id).unwrap();
+        }
+        if checkpointed {
+            trees.checkpoint(&1).unwrap();
+        }
+        let before = trees.clone();
+        trees.begin().unwrap();
+        for (i, (id, value)) in changes.into_iter().enumerate() {
+            trees.set(&1, value, id).unwrap();
+            if checkpointed && i % 7 == 6 {
+                trees.checkpoint(&1).unwrap();
+            }
+        }
+        trees.rollback().unwrap();
+        prop_assert_eq!(&trees, &before);
+    }
+}
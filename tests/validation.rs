@@ -0,0 +1,44 @@
+use sortition_sum_tree::{SortitionError, SortitionSumTrees, Violation};
+
+#[test]
+fn valid_trees_test() {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    for k in 2..=5 {
+        let mut trees: SortitionSumTrees<u64, u64, u64> = SortitionSumTrees::new();
+        trees.create_tree(k, k as usize).unwrap();
+        assert_eq!(trees.validate(&k), Ok(vec![]));
+        for _ in 0..500 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let id = state % 40;
+            let value = if state.is_multiple_of(3) {
+                0
+            } else {
+                state >> 48
+            };
+            trees.set(&k, value, id).unwrap();
+            assert_eq!(trees.validate(&k), Ok(vec![]));
+        }
+    }
+    let trees: SortitionSumTrees = SortitionSumTrees::new();
+    assert_eq!(trees.validate(&1), Err(SortitionError::TreeNotFound));
+}
+
+#[test]
+fn violation_display_test() {
+    let violation: Violation<u64, u64> = Violation::WrongSum {
+        index: 1,
+        value: 99,
+        children_sum: Some(50),
+    };
+    assert_eq!(
+        violation.to_string(),
+        "node 1 is not the sum of its children: 99 != 50"
+    );
+    let violation: Violation<u64, u64> = Violation::Unaccounted { index: 6 };
+    assert_eq!(
+        violation.to_string(),
+        "leaf 6 is neither labelled nor stacked"
+    );
+}
@@ -0,0 +1,50 @@
+pragma solidity ^0.4.24;
+
+import "./SortitionSumTreeFactory.sol";
+
+/**
+ *  @title Harness
+ *  @dev Exposes Kleros' `SortitionSumTreeFactory` library, and the raw storage of its trees, to `replay.py`.
+ */
+contract Harness {
+    using SortitionSumTreeFactory for SortitionSumTreeFactory.SortitionSumTrees;
+
+    SortitionSumTreeFactory.SortitionSumTrees internal sortitionSumTrees;
+
+    function createTree(bytes32 _key, uint _K) public {
+        sortitionSumTrees.createTree(_key, _K);
+    }
+
+    function set(bytes32 _key, uint _value, bytes32 _ID) public {
+        sortitionSumTrees.set(_key, _value, _ID);
+    }
+
+    function queryLeafs(bytes32 _key, uint _cursor, uint _count) public view returns(uint startIndex, uint[] values, bool hasMore) {
+        return sortitionSumTrees.queryLeafs(_key, _cursor, _count);
+    }
+
+    function draw(bytes32 _key, uint _drawnNumber) public view returns(bytes32 ID) {
+        return sortitionSumTrees.draw(_key, _drawnNumber);
+    }
+
+    /** @dev Draw the way `KlerosLiquid.drawJurors` does. */
+    function drawFromSeed(bytes32 _key, uint _RN, uint _disputeID, uint _nonce) public view returns(bytes32 ID) {
+        return sortitionSumTrees.draw(_key, uint(keccak256(abi.encodePacked(_RN, _disputeID, _nonce))));
+    }
+
+    function stakeOf(bytes32 _key, bytes32 _ID) public view returns(uint value) {
+        return sortitionSumTrees.stakeOf(_key, _ID);
+    }
+
+    function nodes(bytes32 _key) public view returns(uint[]) {
+        return sortitionSumTrees.sortitionSumTrees[_key].nodes;
+    }
+
+    function stack(bytes32 _key) public view returns(uint[]) {
+        return sortitionSumTrees.sortitionSumTrees[_key].stack;
+    }
+
+    function nodeIndexesToIDs(bytes32 _key, uint _index) public view returns(bytes32 ID) {
+        return sortitionSumTrees.sortitionSumTrees[_key].nodeIndexesToIDs[_index];
+    }
+}
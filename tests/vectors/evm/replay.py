@@ -0,0 +1,221 @@
+#!/usr/bin/env python3
+"""Replays the conformance scripts against the real `SortitionSumTreeFactory.sol`.
+
+Every step of `tests/vectors/*.json` is run on an EVM through `Harness.sol`,
+and each observable result (reverts, node arrays, stacks, ID labels, stakes,
+draws and leaf pages) is compared with the one recorded by `generate.py`.
+With `--write`, the results read from the EVM are written to
+`tests/vectors/evm-<name>.json`, which `tests/conformance.rs` replays along
+with the other vectors.
+
+Requirements:
+  - `SortitionSumTreeFactory.sol` from kleros/kleros
+    (`contracts/data-structures/SortitionSumTreeFactory.sol`), vendored next
+    to this script; another copy can be passed with `--factory`;
+  - a solc 0.4.x binary (the library pins `^0.4.24`), passed with `--solc`;
+  - a running node with an unlocked, funded account, e.g. `anvil`.
+
+Usage:
+  anvil &
+  python3 tests/vectors/evm/replay.py --solc solc-0.4.26 \\
+      [--rpc http://127.0.0.1:8545] [--write] [name ...]
+"""
+
+import argparse
+import glob
+import json
+import os
+import re
+import shutil
+import subprocess
+import sys
+import tempfile
+import urllib.request
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+VECTORS = os.path.dirname(HERE)
+sys.path.insert(0, VECTORS)
+
+from generate import Recorder, keccak256  # noqa: E402
+
+GAS = hex(30_000_000)
+ERROR_SELECTOR = "08c379a0"
+
+
+class Revert(Exception):
+    pass
+
+
+class Node:
+    def __init__(self, url):
+        self.url = url
+        self.id = 0
+        self.sender = self.rpc("eth_accounts")[0]
+
+    def rpc(self, method, *params):
+        self.id += 1
+        body = json.dumps({"jsonrpc": "2.0", "id": self.id, "method": method, "params": list(params)})
+        request = urllib.request.Request(self.url, body.encode(), {"Content-Type": "application/json"})
+        with urllib.request.urlopen(request) as response:
+            reply = json.load(response)
+        if "error" in reply:
+            raise Revert(revert_reason(reply["error"]))
+        return reply["result"]
+
+    def deploy(self, code):
+        receipt = self.transact({"data": "0x" + code})
+        return receipt["contractAddress"]
+
+    def transact(self, transaction):
+        transaction = dict(transaction, **{"from": self.sender, "gas": GAS})
+        # Calling first reports the revert reason without mining a failed transaction.
+        self.rpc("eth_call", transaction, "latest")
+        receipt = self.rpc("eth_getTransactionReceipt", self.rpc("eth_sendTransaction", transaction))
+        if receipt is None or int(receipt["status"], 16) != 1:
+            raise Revert("transaction failed")
+        return receipt
+
+    def call(self, transaction):
+        return bytes.fromhex(self.rpc("eth_call", dict(transaction, **{"from": self.sender, "gas": GAS}), "latest")[2:])
+
+
+def revert_reason(error):
+    """The `require` message of a revert, like `generate.py` records it."""
+    data = error.get("data")
+    if isinstance(data, dict):
+        data = data.get("data")
+    if isinstance(data, str) and data[2:10] == ERROR_SELECTOR:
+        payload = bytes.fromhex(data[10:])
+        length = int.from_bytes(payload[32:64], "big")
+        return payload[64:64 + length].decode()
+    # Out-of-bounds reads and modulo by zero hit the invalid opcode in solc 0.4.
+    return "invalid opcode"
+
+
+def word(value):
+    return value.to_bytes(32, "big")
+
+
+def words(data):
+    return [int.from_bytes(data[i:i + 32], "big") for i in range(0, len(data), 32)]
+
+
+def uint_array(data, offset):
+    length = int.from_bytes(data[offset:offset + 32], "big")
+    return words(data[offset + 32:offset + 32 + 32 * length])
+
+
+class Harness:
+    def __init__(self, node, code):
+        self.node = node
+        self.address = node.deploy(code)
+
+    def _data(self, signature, *args):
+        return "0x" + (keccak256(signature.encode())[:4] + b"".join(word(arg) for arg in args)).hex()
+
+    def transact(self, signature, *args):
+        self.node.transact({"to": self.address, "data": self._data(signature, *args)})
+
+    def call(self, signature, *args):
+        return self.node.call({"to": self.address, "data": self._data(signature, *args)})
+
+
+def compile_harness(factory, solc):
+    """Compiles the harness and returns the code of the library and of the harness, with a placeholder left for the library address."""
+    with tempfile.TemporaryDirectory() as directory:
+        shutil.copy(factory, os.path.join(directory, "SortitionSumTreeFactory.sol"))
+        shutil.copy(os.path.join(HERE, "Harness.sol"), directory)
+        output = subprocess.run(
+            [solc, "--optimize", "--combined-json", "bin", "Harness.sol"],
+            cwd=directory, check=True, capture_output=True, text=True,
+        ).stdout
+    contracts = json.loads(output)["contracts"]
+    library = next(code["bin"] for name, code in contracts.items() if name.endswith(":SortitionSumTreeFactory"))
+    harness = next(code["bin"] for name, code in contracts.items() if name.endswith(":Harness"))
+    return library, harness
+
+
+def replay(node, code, script):
+    """Runs a script on a fresh harness and returns its steps with the results read from the EVM."""
+    harness = Harness(node, code)
+    observed = []
+    for step in script["steps"]:
+        op, key = step["op"], int(step["key"])
+        result = {name: value for name, value in step.items() if name not in ("expected", "expectRevert")}
+        if op == "checkTree":
+            nodes = uint_array(harness.call("nodes(bytes32)", key), 32)
+            stack = uint_array(harness.call("stack(bytes32)", key), 32)
+            ids = []
+            for index in range(len(nodes)):
+                ID = words(harness.call("nodeIndexesToIDs(bytes32,uint256)", key, index))[0]
+                if ID != 0:
+                    ids.append([index, str(ID)])
+            result.update(nodes=[str(node) for node in nodes], stack=stack, ids=ids)
+            observed.append(result)
+            continue
+        try:
+            if op == "createTree":
+                harness.transact("createTree(bytes32,uint256)", key, step["k"])
+            elif op == "set":
+                harness.transact("set(bytes32,uint256,bytes32)", key, int(step["value"]), int(step["id"]))
+            elif op == "stakeOf":
+                result["expected"] = str(words(harness.call("stakeOf(bytes32,bytes32)", key, int(step["id"])))[0])
+            elif op == "draw":
+                result["expected"] = str(words(harness.call("draw(bytes32,uint256)", key, int(step["drawnNumber"])))[0])
+            elif op == "drawFromSeed":
+                seed = int(step["seed"], 16)
+                data = harness.call("drawFromSeed(bytes32,uint256,uint256,uint256)", key, seed, int(step["disputeId"]), step["nonce"])
+                result["expected"] = str(words(data)[0])
+            elif op == "queryLeafs":
+                data = harness.call("queryLeafs(bytes32,uint256,uint256)", key, step["cursor"], step["count"])
+                start_index, offset, has_more = words(data[:96])
+                values = uint_array(data, offset)
+                result["expected"] = {"startIndex": start_index, "values": [str(value) for value in values], "hasMore": bool(has_more)}
+            else:
+                raise ValueError("unknown step %r" % op)
+        except Revert as revert:
+            result.pop("expected", None)
+            result["expectRevert"] = str(revert)
+        observed.append(result)
+    return observed
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument(
+        "--factory",
+        default=os.path.join(HERE, "SortitionSumTreeFactory.sol"),
+        help="path to SortitionSumTreeFactory.sol",
+    )
+    parser.add_argument("--solc", default="solc", help="solc 0.4.x binary")
+    parser.add_argument("--rpc", default="http://127.0.0.1:8545", help="JSON-RPC endpoint of the node")
+    parser.add_argument("--write", action="store_true", help="write the EVM results to tests/vectors/evm-<name>.json")
+    parser.add_argument("names", nargs="*", help="scripts to replay (default: all)")
+    args = parser.parse_args()
+
+    node = Node(args.rpc)
+    library, harness = compile_harness(args.factory, args.solc)
+    library_address = node.deploy(library)[2:].lower()
+    code = re.sub(r"__.{36}__", library_address, harness)
+
+    mismatches = 0
+    for path in sorted(glob.glob(os.path.join(VECTORS, "*.json"))):
+        script = json.load(open(path))
+        if script["name"].startswith("evm-") or (args.names and script["name"] not in args.names):
+            continue
+        observed = replay(node, code, script)
+        for i, (recorded, actual) in enumerate(zip(script["steps"], observed)):
+            if recorded != actual:
+                mismatches += 1
+                print("%s step %d:\n  recorded %s\n  evm      %s" % (script["name"], i, json.dumps(recorded), json.dumps(actual)))
+        print("%s: %d steps replayed" % (script["name"], len(observed)))
+        if args.write:
+            recorder = Recorder("evm-" + script["name"], "Recorded on an EVM from SortitionSumTreeFactory.sol. " + script["description"])
+            recorder.steps = observed
+            recorder.write(VECTORS)
+    print("%d mismatches" % mismatches)
+    sys.exit(1 if mismatches else 0)
+
+
+if __name__ == "__main__":
+    main()
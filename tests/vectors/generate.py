@@ -0,0 +1,471 @@
+#!/usr/bin/env python3
+"""Generates the conformance vectors replayed by `tests/conformance.rs`.
+
+`SortitionSumTreeFactory` below is a statement-by-statement transliteration
+of Kleros' `SortitionSumTreeFactory.sol` (solidity ^0.4.24): storage
+mappings default to 0, `require` and out-of-bounds reads revert, and every
+value is a uint256. Each script is run against it and every observable
+result (reverts, node arrays, stacks, ID labels, stakes, draws and leaf
+pages) is recorded as the expected value.
+
+The vectors checked in under this name come from the transliteration, not
+from an EVM. `evm/replay.py` runs the same scripts against the contract
+vendored in `evm/` (solc 0.4 and anvil), reports every result that differs,
+and with `--write` records the EVM results as `evm-<name>.json`, which
+`tests/conformance.rs` requires.
+
+Usage: python3 tests/vectors/generate.py  (rewrites tests/vectors/*.json)
+"""
+
+import copy
+import json
+import os
+import random
+
+UINT256 = 2**256
+
+
+class Revert(Exception):
+    pass
+
+
+# Keccak-256 (the pre-standard padding used by Solidity's `keccak256`).
+
+_RC = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808A, 0x8000000080008000,
+    0x000000000000808B, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008A, 0x0000000000000088, 0x0000000080008009, 0x000000008000000A,
+    0x000000008000808B, 0x800000000000008B, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800A, 0x800000008000000A,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+]
+_ROT = [
+    [0, 36, 3, 41, 18],
+    [1, 44, 10, 45, 2],
+    [62, 6, 43, 15, 61],
+    [28, 55, 25, 21, 56],
+    [27, 20, 39, 8, 14],
+]
+_MASK = 2**64 - 1
+
+
+def _rol(value, shift):
+    return ((value << shift) | (value >> (64 - shift))) & _MASK if shift else value
+
+
+def _keccak_f(state):
+    for rc in _RC:
+        c = [state[x][0] ^ state[x][1] ^ state[x][2] ^ state[x][3] ^ state[x][4] for x in range(5)]
+        d = [c[(x - 1) % 5] ^ _rol(c[(x + 1) % 5], 1) for x in range(5)]
+        state = [[state[x][y] ^ d[x] for y in range(5)] for x in range(5)]
+        b = [[0] * 5 for _ in range(5)]
+        for x in range(5):
+            for y in range(5):
+                b[y][(2 * x + 3 * y) % 5] = _rol(state[x][y], _ROT[x][y])
+        state = [[b[x][y] ^ (~b[(x + 1) % 5][y] & b[(x + 2) % 5][y]) for y in range(5)] for x in range(5)]
+        state[0][0] ^= rc
+    return state
+
+
+def keccak256(data):
+    rate = 136
+    padded = bytearray(data) + b"\x01"
+    padded += b"\x00" * (-len(padded) % rate)
+    padded[-1] |= 0x80
+    state = [[0] * 5 for _ in range(5)]
+    for offset in range(0, len(padded), rate):
+        block = padded[offset:offset + rate]
+        for i in range(rate // 8):
+            x, y = i % 5, i // 5
+            state[x][y] ^= int.from_bytes(block[8 * i:8 * i + 8], "little")
+        state = _keccak_f(state)
+    out = b"".join(state[i % 5][i // 5].to_bytes(8, "little") for i in range(4))
+    return out
+
+
+assert keccak256(b"").hex() == "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+assert keccak256(bytes(64)).hex() == "ad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5"
+
+
+def drawn_number(seed, dispute_id, nonce):
+    """uint256(keccak256(abi.encodePacked(uint256 RN, uint256 disputeID, uint256 nonce)))"""
+    packed = seed + dispute_id.to_bytes(32, "big") + nonce.to_bytes(32, "big")
+    return int.from_bytes(keccak256(packed), "big")
+
+
+# SortitionSumTreeFactory.sol
+
+
+class SortitionSumTree:
+    def __init__(self):
+        self.K = 0
+        self.stack = []
+        self.nodes = []
+        self.IDsToNodeIndexes = {}
+        self.nodeIndexesToIDs = {}
+
+
+class SortitionSumTreeFactory:
+    def __init__(self):
+        self.sortitionSumTrees = {}
+
+    def _tree(self, key):
+        return self.sortitionSumTrees.setdefault(key, SortitionSumTree())
+
+    @staticmethod
+    def _at(array, index):
+        if index >= len(array):
+            raise Revert("invalid opcode")
+        return array[index]
+
+    def createTree(self, key, K):
+        tree = self._tree(key)
+        if tree.K != 0:
+            raise Revert("Tree already exists.")
+        if K <= 1:
+            raise Revert("K must be greater than one.")
+        tree.K = K
+        tree.stack = []
+        tree.nodes = []
+        tree.nodes.append(0)
+
+    def set(self, key, value, ID):
+        tree = self._tree(key)
+        treeIndex = tree.IDsToNodeIndexes.get(ID, 0)
+
+        if treeIndex == 0:  # No existing node.
+            if value != 0:  # Non zero value.
+                # Append.
+                # Add node.
+                if len(tree.stack) == 0:  # No vacant spots.
+                    # Get the index and append the value.
+                    treeIndex = len(tree.nodes)
+                    tree.nodes.append(value)
+
+                    # Potentially append a new node and make the parent a sum node.
+                    if tree.K == 0:
+                        raise Revert("invalid opcode")  # Modulo by zero.
+                    if treeIndex != 1 and (treeIndex - 1) % tree.K == 0:  # Is first child.
+                        parentIndex = treeIndex // tree.K
+                        parentID = tree.nodeIndexesToIDs.get(parentIndex, 0)
+                        newIndex = treeIndex + 1
+                        tree.nodes.append(self._at(tree.nodes, parentIndex))
+                        tree.nodeIndexesToIDs.pop(parentIndex, None)
+                        tree.IDsToNodeIndexes[parentID] = newIndex
+                        tree.nodeIndexesToIDs[newIndex] = parentID
+                else:  # Some vacant spot.
+                    # Pop the stack and append the value.
+                    treeIndex = tree.stack[-1]
+                    tree.stack.pop()
+                    tree.nodes[treeIndex] = value
+
+                # Add label.
+                tree.IDsToNodeIndexes[ID] = treeIndex
+                tree.nodeIndexesToIDs[treeIndex] = ID
+
+                self.updateParents(key, treeIndex, True, value)
+        else:  # Existing node.
+            if value == 0:  # Zero value.
+                # Remove.
+                # Remember value and set to 0.
+                value = tree.nodes[treeIndex]
+                tree.nodes[treeIndex] = 0
+
+                # Push to stack.
+                tree.stack.append(treeIndex)
+
+                # Clear label.
+                tree.IDsToNodeIndexes.pop(ID, None)
+                tree.nodeIndexesToIDs.pop(treeIndex, None)
+
+                self.updateParents(key, treeIndex, False, value)
+            elif value != tree.nodes[treeIndex]:  # New, non zero value.
+                # Set.
+                plusOrMinus = tree.nodes[treeIndex] <= value
+                plusOrMinusValue = value - tree.nodes[treeIndex] if plusOrMinus else tree.nodes[treeIndex] - value
+                tree.nodes[treeIndex] = value
+
+                self.updateParents(key, treeIndex, plusOrMinus, plusOrMinusValue)
+
+    def queryLeafs(self, key, cursor, count):
+        tree = self._tree(key)
+
+        # Find the start index.
+        startIndex = 0
+        for i in range(len(tree.nodes)):
+            if (tree.K * i) + 1 >= len(tree.nodes):
+                startIndex = i
+                break
+
+        # Get the values.
+        loopStartIndex = startIndex + cursor
+        size = len(tree.nodes) - loopStartIndex if loopStartIndex + count > len(tree.nodes) else count
+        if size < 0:
+            raise Revert("invalid opcode")  # uint underflow allocating the array.
+        values = [0] * size
+        valuesIndex = 0
+        hasMore = False
+        for j in range(loopStartIndex, len(tree.nodes)):
+            if valuesIndex < count:
+                values[valuesIndex] = tree.nodes[j]
+                valuesIndex += 1
+            else:
+                hasMore = True
+                break
+        return startIndex, values, hasMore
+
+    def draw(self, key, drawnNumber):
+        tree = self._tree(key)
+        treeIndex = 0
+        if self._at(tree.nodes, 0) == 0:
+            raise Revert("invalid opcode")  # Modulo by zero.
+        currentDrawnNumber = drawnNumber % tree.nodes[0]
+
+        while (tree.K * treeIndex) + 1 < len(tree.nodes):  # While it still has children.
+            for i in range(1, tree.K + 1):  # Loop over children.
+                nodeIndex = (tree.K * treeIndex) + i
+                nodeValue = self._at(tree.nodes, nodeIndex)
+
+                if currentDrawnNumber >= nodeValue:
+                    currentDrawnNumber -= nodeValue  # Go to the next child.
+                else:  # Pick this child.
+                    treeIndex = nodeIndex
+                    break
+
+        return tree.nodeIndexesToIDs.get(treeIndex, 0)
+
+    def stakeOf(self, key, ID):
+        tree = self._tree(key)
+        treeIndex = tree.IDsToNodeIndexes.get(ID, 0)
+
+        if treeIndex == 0:
+            return 0
+        return tree.nodes[treeIndex]
+
+    def updateParents(self, key, treeIndex, plusOrMinus, value):
+        tree = self._tree(key)
+
+        parentIndex = treeIndex
+        while parentIndex != 0:
+            parentIndex = (parentIndex - 1) // tree.K
+            if plusOrMinus:
+                tree.nodes[parentIndex] = (tree.nodes[parentIndex] + value) % UINT256
+            else:
+                tree.nodes[parentIndex] = (tree.nodes[parentIndex] - value) % UINT256
+
+
+# Script recording.
+
+
+class Recorder:
+    def __init__(self, name, description):
+        self.name = name
+        self.description = description
+        self.factory = SortitionSumTreeFactory()
+        self.steps = []
+
+    def _call(self, step, call):
+        state = copy.deepcopy(self.factory.sortitionSumTrees)
+        try:
+            result = call()
+        except Revert as revert:
+            # A revert rolls back every write of the call.
+            self.factory.sortitionSumTrees = state
+            step["expectRevert"] = str(revert)
+            self.steps.append(step)
+            return None
+        return result
+
+    def create_tree(self, key, k):
+        step = {"op": "createTree", "key": str(key), "k": k}
+        self._call(step, lambda: self.factory.createTree(key, k))
+        if "expectRevert" not in step:
+            self.steps.append(step)
+
+    def set(self, key, value, ID):
+        step = {"op": "set", "key": str(key), "value": str(value), "id": str(ID)}
+        self._call(step, lambda: self.factory.set(key, value, ID))
+        if "expectRevert" not in step:
+            self.steps.append(step)
+
+    def stake_of(self, key, ID):
+        step = {"op": "stakeOf", "key": str(key), "id": str(ID)}
+        result = self._call(step, lambda: self.factory.stakeOf(key, ID))
+        if "expectRevert" not in step:
+            step["expected"] = str(result)
+            self.steps.append(step)
+
+    def draw(self, key, number):
+        step = {"op": "draw", "key": str(key), "drawnNumber": str(number)}
+        result = self._call(step, lambda: self.factory.draw(key, number))
+        if "expectRevert" not in step:
+            step["expected"] = str(result)
+            self.steps.append(step)
+
+    def draw_from_seed(self, key, seed, dispute_id, nonce):
+        step = {"op": "drawFromSeed", "key": str(key), "seed": seed.hex(), "disputeId": str(dispute_id), "nonce": nonce}
+        result = self._call(step, lambda: self.factory.draw(key, drawn_number(seed, dispute_id, nonce)))
+        if "expectRevert" not in step:
+            step["expected"] = str(result)
+            self.steps.append(step)
+
+    def query_leafs(self, key, cursor, count):
+        step = {"op": "queryLeafs", "key": str(key), "cursor": cursor, "count": count}
+        result = self._call(step, lambda: self.factory.queryLeafs(key, cursor, count))
+        if "expectRevert" not in step:
+            startIndex, values, hasMore = result
+            step["expected"] = {"startIndex": startIndex, "values": [str(value) for value in values], "hasMore": hasMore}
+            self.steps.append(step)
+
+    def query_all_leafs(self, key):
+        """Pages through every leaf, in windows of several sizes while no leaf is vacant."""
+        tree = self.factory._tree(key)
+        leaves = len(tree.nodes)
+        self.query_leafs(key, 0, leaves)
+        if not tree.stack:
+            for count in (2, 5):
+                for cursor in range(0, leaves, count):
+                    self.query_leafs(key, cursor, count)
+                    if not self.steps[-1]["expected"]["hasMore"]:
+                        break
+
+    def check_tree(self, key):
+        tree = self.factory._tree(key)
+        self.steps.append({
+            "op": "checkTree",
+            "key": str(key),
+            "nodes": [str(node) for node in tree.nodes],
+            "stack": list(tree.stack),
+            "ids": [[index, str(ID)] for index, ID in sorted(tree.nodeIndexesToIDs.items()) if ID != 0],
+        })
+
+    def write(self, directory):
+        path = os.path.join(directory, self.name + ".json")
+        steps = ",\n".join("  " + json.dumps(step) for step in self.steps)
+        with open(path, "w") as file:
+            file.write("{\n")
+            file.write(' "name": %s,\n' % json.dumps(self.name))
+            file.write(' "description": %s,\n' % json.dumps(self.description))
+            file.write(' "steps": [\n%s\n ]\n}\n' % steps)
+
+
+def four_equal_stakes():
+    r = Recorder("four-equal-stakes", "The three original hand-written tests: K=2, four stakes of 25.")
+    r.create_tree(1, 2)
+    for ID in range(1, 5):
+        r.set(1, 25, ID)
+    r.check_tree(1)
+    for number in (20, 40, 60, 80):
+        r.draw(1, number)
+    r.set(1, 0, 3)
+    r.stake_of(1, 3)
+    r.check_tree(1)
+    r.query_all_leafs(1)
+    r.set(1, 25, 5)
+    r.check_tree(1)
+    r.query_all_leafs(1)
+    return r
+
+
+def reverts():
+    r = Recorder("reverts", "Calls the Solidity library reverts on.")
+    r.create_tree(1, 1)
+    r.create_tree(1, 0)
+    r.create_tree(1, 2)
+    r.query_all_leafs(1)
+    r.create_tree(1, 3)
+    r.draw(1, 0)
+    r.draw(2, 0)
+    r.set(2, 10, 1)
+    r.set(1, 10, 1)
+    r.set(1, 0, 1)
+    r.draw(1, 7)
+    r.check_tree(1)
+    return r
+
+
+def relocation(k, count):
+    r = Recorder(
+        "relocation-k%d" % k,
+        "K=%d: every append that creates a first child moves its parent leaf down; the tree is checked after each set." % k,
+    )
+    r.create_tree(7, k)
+    for ID in range(1, count + 1):
+        r.set(7, ID * 10 + k, ID)
+        r.check_tree(7)
+        if ID % 7 == 0:
+            r.query_all_leafs(7)
+    for ID in range(1, count + 1):
+        r.stake_of(7, ID)
+    total = r.factory._tree(7).nodes[0]
+    for number in range(0, total, max(1, total // 97)):
+        r.draw(7, number)
+    r.draw(7, total - 1)
+    r.draw(7, total)
+    return r
+
+
+def stack_reuse(k):
+    r = Recorder(
+        "stack-reuse-k%d" % k,
+        "K=%d: removals push vacant leaves, later insertions pop them last in, first out, including leaves that were moved down." % k,
+    )
+    r.create_tree(3, k)
+    for ID in range(1, 15):
+        r.set(3, 100 + ID, ID)
+    r.check_tree(3)
+    for ID in (1, 2, 5, 9, 14):
+        r.set(3, 0, ID)
+        r.check_tree(3)
+        r.query_all_leafs(3)
+    for ID in (2, 9):
+        r.stake_of(3, ID)
+    for ID in range(20, 27):
+        r.set(3, 7 * ID, ID)
+        r.check_tree(3)
+        r.query_all_leafs(3)
+    for ID in (3, 20, 26):
+        r.set(3, 1, ID)
+        r.set(3, 3000, ID)
+    r.check_tree(3)
+    total = r.factory._tree(3).nodes[0]
+    for number in range(0, total, max(1, total // 61)):
+        r.draw(3, number)
+    return r
+
+
+def random_script(k, seed, ops):
+    rng = random.Random(seed)
+    r = Recorder(
+        "random-k%d-seed%d" % (k, seed),
+        "K=%d: %d pseudo-random sets over 40 IDs (a third of them removals) with draws, seeded draws and 256-bit drawn numbers." % (k, ops),
+    )
+    r.create_tree(seed, k)
+    for step in range(ops):
+        ID = rng.randrange(1, 41)
+        value = 0 if rng.random() < 0.33 else rng.choice([1, rng.randrange(1, 1000), rng.randrange(1, 2**64)])
+        r.set(seed, value, ID)
+        if step % 10 == 9:
+            r.check_tree(seed)
+            r.stake_of(seed, rng.randrange(1, 41))
+            r.draw(seed, rng.randrange(0, 2**64))
+            r.draw(seed, rng.randrange(0, UINT256))
+            r.draw_from_seed(seed, bytes(rng.randrange(256) for _ in range(32)), step // 10, step)
+        if step % 50 == 49:
+            r.query_all_leafs(seed)
+    r.check_tree(seed)
+    return r
+
+
+def main():
+    directory = os.path.dirname(os.path.abspath(__file__))
+    recorders = [four_equal_stakes(), reverts()]
+    recorders += [relocation(k, 30) for k in (2, 3, 4, 5)]
+    recorders += [stack_reuse(k) for k in (2, 3, 7)]
+    recorders += [random_script(k, seed, 200) for k, seed in ((2, 1), (3, 2), (4, 3), (6, 4), (16, 5))]
+    for recorder in recorders:
+        recorder.write(directory)
+
+
+if __name__ == "__main__":
+    main()